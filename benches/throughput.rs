@@ -0,0 +1,94 @@
+//! Measures send and receive performance over loopback.
+//!
+//! Uses a minimal harness instead of criterion or `#[bench]` to work on
+//! stable Rust and not add any dev-dependencies.
+//! Run with `cargo bench`, optionally followed by `-- <substring of name>`
+//! to only run some of the benchmarks.
+
+extern crate udplite;
+
+use std::env;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::Instant;
+use udplite::UdpLiteSocket;
+
+const ITERATIONS: u32 = 20_000;
+const BATCH: usize = 32;
+
+fn bench<F: FnMut()>(name: &str,  datagrams_per_iter: u32,  mut f: F) {
+    if let Some(filter) = env::args().skip(1).find(|arg| !arg.starts_with('-')) {
+        if !name.contains(&filter) {
+            return;
+        }
+    }
+    // warm up
+    for _ in 0..ITERATIONS/10 {
+        f();
+    }
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let elapsed = start.elapsed();
+    let per_datagram = elapsed.as_nanos() as f64
+        / f64::from(ITERATIONS)
+        / f64::from(datagrams_per_iter);
+    println!("{:<45} {:>8.0} ns/datagram", name, per_datagram);
+}
+
+fn socket_pair(coverage: Option<u16>) -> (UdpLiteSocket, UdpLiteSocket, SocketAddr) {
+    let a = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0)).expect("create sending socket");
+    let b = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0)).expect("create receiving socket");
+    a.set_send_checksum_coverage(coverage).expect("set send cscov");
+    b.set_recv_checksum_coverage_filter(coverage).expect("set recv cscov filter");
+    let b_addr = b.local_addr().expect("get addr of receiving socket");
+    (a, b, b_addr)
+}
+
+fn send_recv(name: &str,  coverage: Option<u16>,  len: usize) {
+    let (a, b, b_addr) = socket_pair(coverage);
+    let msg = vec![b'x'; len];
+    let mut buf = vec![0; len];
+    bench(name, 1, || {
+        a.send_to(&msg, b_addr).expect("send datagram");
+        b.recv_from(&mut buf).expect("receive datagram");
+    });
+}
+
+/// `send()` on a connected socket goes straight to `send(2)`, without
+/// converting a destination address for every datagram.
+fn connected_send_recv(name: &str,  coverage: Option<u16>,  len: usize) {
+    let (a, b, b_addr) = socket_pair(coverage);
+    a.connect(b_addr).expect("connect sending socket");
+    let msg = vec![b'x'; len];
+    let mut buf = vec![0; len];
+    bench(name, 1, || {
+        a.send(&msg).expect("send datagram");
+        b.recv_from(&mut buf).expect("receive datagram");
+    });
+}
+
+fn send_many_recv(name: &str,  coverage: Option<u16>,  len: usize) {
+    let (a, b, b_addr) = socket_pair(coverage);
+    let msg = vec![b'x'; len];
+    let msgs = vec![(b_addr, &msg[..]); BATCH];
+    let mut buf = vec![0; len];
+    bench(name, BATCH as u32, || {
+        a.send_all_to(&msgs).expect("send datagrams");
+        for _ in 0..BATCH {
+            b.recv_from(&mut buf).expect("receive datagram");
+        }
+    });
+}
+
+fn main() {
+    send_recv("send_to+recv_from 64B full coverage", None, 64);
+    send_recv("send_to+recv_from 64B 8B coverage", Some(8), 64);
+    send_recv("send_to+recv_from 1400B full coverage", None, 1400);
+    send_recv("send_to+recv_from 1400B 8B coverage", Some(8), 1400);
+    connected_send_recv("connected send+recv_from 64B full coverage", None, 64);
+    connected_send_recv("connected send+recv_from 1400B full coverage", None, 1400);
+    send_many_recv("send_all_to+recv_from 64B full coverage", None, 64);
+    send_many_recv("send_all_to+recv_from 1400B full coverage", None, 1400);
+    send_many_recv("send_all_to+recv_from 1400B 8B coverage", Some(8), 1400);
+}
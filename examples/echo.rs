@@ -0,0 +1,41 @@
+//! Echo datagrams between two sockets, with only the first 8 bytes of
+//! the payload covered by the checksum.
+
+extern crate udplite;
+
+use std::thread;
+use std::time::Duration;
+use udplite::UdpLiteSocket;
+
+fn main() {
+    let server = UdpLiteSocket::bind("127.0.0.1:0").expect("create server socket");
+    // cover the 4-byte sequence number and 4-byte length
+    server.set_checksum_coverage(Some(8), Some(8)).expect("set server cscov");
+    let server_addr = server.local_addr().unwrap();
+    thread::spawn(move || {
+        let mut buf = [0; 1024];
+        loop {
+            let (len, from) = server.recv_from(&mut buf).expect("receive request");
+            server.send_to(&buf[..len], from).expect("echo datagram");
+        }
+    });
+
+    let client = UdpLiteSocket::bind("127.0.0.1:0").expect("create client socket");
+    client.connect_with_coverage(server_addr, Some(8)).expect("connect to server");
+    client.set_recv_checksum_coverage_filter(Some(8)).expect("set client recv cscov filter");
+    client.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+
+    let mut buf = [0; 1024];
+    for seq in 0..3u32 {
+        let payload = format!("echo #{}: errors here are tolerated", seq);
+        let mut request = Vec::new();
+        request.extend_from_slice(&seq.to_be_bytes());
+        request.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        request.extend_from_slice(payload.as_bytes());
+        client.send(&request).expect("send request");
+        match client.recv(&mut buf) {
+            Ok(len) => println!("received {}", String::from_utf8_lossy(&buf[8..len])),
+            Err(e) => println!("no reply for #{}: {}", seq, e),
+        }
+    }
+}
@@ -0,0 +1,610 @@
+/* Copyright 2020 Torbjørn Birch Moltu
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+
+//! Exposes the UDP-Lite socket type with an API similar to `std::net::UdpSocket`.
+//!
+//! UDP-Lite is a layer 3 networking protocol very similar to UDP, that allows
+//! receiving partially corrupted packets.
+//! 
+//! In addition to not being reliable (ie. datagrams can disappear), 
+//! UDP-Lite is only useful if the layer 2 protocol supports disabling checksums,
+//! and is not all that usable on the wider internet.
+//! (My ISPs router doesn't recognize the protocol, so its NAT drops all packets.)
+//! The protocol is also only implemented on Linux and FreeBSD.
+//! (It looks like Android hasn't disabled it, but I'm not certain).
+//!
+//! This crate is tested on both Linux (except Android) and FreeBSD.
+//! On other operating systems the types and functions still exist,
+//! but all sockets fail to be created with `ErrorKind::Unsupported`.
+//!
+//! # Examples
+//!
+//! ```
+//! use udplite::UdpLiteSocket;
+//! use std::net::*;
+//!
+//! let a = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+//!     .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+//! let b = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+//!     .expect("create another socket bound to 127.0.0.1:0");
+//!
+//! // reduce sent and required checksum coverage (whole datagram by default)
+//! a.set_send_checksum_coverage(Some(5)).expect("set partial checksum coverage");
+//! b.set_recv_checksum_coverage_filter(Some(5)).expect("set required checksum coverage");
+//!
+//! let b_addr = b.local_addr().expect("get addr of socket b");
+//! a.send_to(b"Hello UDP-Lite", b_addr).expect("send datagram");
+//!
+//! # b.set_nonblocking(true).expect("enable non-blocking for receiving just in case");
+//! let mut buf = [0u8; 20];
+//! let received_bytes = b.recv(&mut buf).expect("receive datagram");
+//! assert_eq!(received_bytes, "Hello UDP-Lite".len());
+//! assert_eq!(&buf[..5], b"Hello");
+//! ```
+//!
+//! # Current implementation details
+//!
+//! To significantly reduce the amount of `unsafe` code necessary in this crate,
+//! most methods are provided through `Deref` to [`UdpSocket`](https://doc.rust-lang.org/std/net/struct.UdpSocket.html).
+//! This creates one wart/gotcha/unsoundness though:
+//! `UdpSocket`s `.try_clone()` is available, returning an `UdpSocket` that is
+//! actually UDP-Lite. The method is shadowed by [`UdpLiteSocket`](struct.UdpLiteSocket.html)s
+//! own [`.try_clone()`]()(struct.UdpLiteSocket.html#method.try_clone)
+//!
+//! # Minimum Rust version
+//!
+//! udplite requires Rust 1.53.0 (for `io::ErrorKind::Unsupported`).
+//!
+//! # Possible future features (open an issue if you want one)
+//!
+//! * Optional tokio integration, including a `Stream` of received datagrams
+//!   (like [`incoming()`](struct.UdpLiteSocket.html#method.incoming)).
+//! * Vectored I/O (`std`s `UdpSocket` doesn't have this yet either).
+//! * Exposing more POSIX socket options.
+
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, IntoRawFd, RawFd};
+use std::net::{UdpSocket, SocketAddr, IpAddr, ToSocketAddrs};
+use std::time::SystemTime;
+use std::io::{self, ErrorKind::*};
+use std::{error, fmt};
+use std::ops::Deref;
+#[cfg(feature="serde")]
+use serde::{Serialize, Deserialize};
+
+#[cfg(any(target_os="linux", target_os="freebsd", target_os="android"))]
+mod udplite;
+#[cfg(any(target_os="linux", target_os="freebsd", target_os="android"))]
+pub use udplite::*;
+
+#[cfg(not(any(target_os="linux", target_os="freebsd", target_os="android")))]
+mod unsupported;
+#[cfg(not(any(target_os="linux", target_os="freebsd", target_os="android")))]
+pub use unsupported::*;
+
+
+
+/// The IP version of a socket or address.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum AddressFamily {
+    /// IPv4 (`AF_INET`)
+    Ipv4,
+    /// IPv6 (`AF_INET6`)
+    Ipv6,
+}
+
+impl From<&SocketAddr> for AddressFamily {
+    fn from(addr: &SocketAddr) -> Self {
+        match addr {
+            SocketAddr::V4(_) => AddressFamily::Ipv4,
+            SocketAddr::V6(_) => AddressFamily::Ipv6,
+        }
+    }
+}
+
+/// Get the checksum coverage that covers a header of `header_len` bytes
+/// at the start of the payload, and nothing after it.
+///
+/// The returned value is meant for
+/// [`set_send_checksum_coverage()`](struct.UdpLiteSocket.html#method.set_send_checksum_coverage)
+/// and [`set_recv_checksum_coverage_filter()`](struct.UdpLiteSocket.html#method.set_recv_checksum_coverage_filter),
+/// which count bytes of payload and add the 8-byte UDP-Lite header themselves.
+/// Headers too long for that to be representable can only be covered by
+/// covering the entire datagram, so `None` is returned for them.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(udplite::header_coverage(12), Some(12));
+/// assert_eq!(udplite::header_coverage(0), Some(udplite::UdpLiteSocket::MIN_COVERAGE));
+/// assert_eq!(udplite::header_coverage(0xffff), None);
+/// ```
+pub fn header_coverage(header_len: u16) -> Option<u16> {
+    if header_len <= 0xffff - 8 {
+        Some(header_len)
+    } else {
+        None
+    }
+}
+
+/// Get the fraction of a datagram that the checksum covers, from 0.0 to 1.0.
+///
+/// Like elsewhere in this crate, `coverage` and `datagram_len` count bytes
+/// of payload, but the fraction is of the entire UDP-Lite datagram,
+/// including its always-covered 8-byte header, as the checksum coverage
+/// field on the wire does.
+/// `None` and coverage longer than the datagram are full coverage.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(udplite::coverage_fraction(None, 100), 1.0);
+/// assert_eq!(udplite::coverage_fraction(Some(200), 100), 1.0);
+/// assert_eq!(udplite::coverage_fraction(Some(0), 24), 0.25);
+/// assert_eq!(udplite::coverage_fraction(Some(12), 32), 0.5);
+/// ```
+pub fn coverage_fraction(coverage: Option<u16>,  datagram_len: u16) -> f32 {
+    match coverage {
+        Some(coverage) if coverage < datagram_len => {
+            (coverage as f32 + 8.0) / (datagram_len as f32 + 8.0)
+        }
+        _ => 1.0,
+    }
+}
+
+/// Check whether the receive checksum coverage filter also discards datagrams
+/// with *higher* coverage than the filter.
+///
+/// Linux only discards datagrams with lower coverage than the filter,
+/// while FreeBSD requires the coverage to match the filter exactly.
+///
+/// See [`set_recv_checksum_coverage_filter()`](struct.UdpLiteSocket.html#method.set_recv_checksum_coverage_filter).
+pub const fn recv_filter_is_exact_match() -> bool {
+    cfg!(target_os="freebsd")
+}
+
+
+
+/// An endless iterator of received datagrams, created by
+/// [`UdpLiteSocket.incoming()`](struct.UdpLiteSocket.html#method.incoming).
+pub struct Incoming<'a> {
+    socket: &'a UdpLiteSocket,
+    /// Reused for every datagram, which is then copied into a `Vec` of its
+    /// own length.
+    buf: Vec<u8>,
+}
+
+impl<'a> Incoming<'a> {
+    /// Set the size of the buffer datagrams are received into.
+    ///
+    /// Longer datagrams will be truncated.
+    pub fn max_len(mut self,  max_len: usize) -> Self {
+        self.buf = vec![0; max_len];
+        self
+    }
+}
+
+impl<'a> fmt::Debug for Incoming<'a> {
+    fn fmt(&self,  fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.debug_struct("Incoming")
+            .field("socket", &self.socket)
+            .field("max_len", &self.buf.len())
+            .finish()
+    }
+}
+
+impl<'a> Iterator for Incoming<'a> {
+    type Item = Result<(Vec<u8>, SocketAddr), io::Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(match self.socket.recv_from(&mut self.buf) {
+            Ok((len, from)) => Ok((self.buf[..len].to_vec(), from)),
+            Err(e) => Err(e),
+        })
+    }
+}
+
+
+/// Restores the previous checksum coverage of sent datagrams when dropped,
+/// created by [`UdpLiteSocket.with_send_coverage()`](struct.UdpLiteSocket.html#method.with_send_coverage).
+#[derive(Debug)]
+#[must_use = "the coverage is restored immediately if the guard isn't kept"]
+pub struct CoverageGuard<'a> {
+    socket: &'a UdpLiteSocket,
+    previous: Option<u16>,
+}
+
+impl<'a> CoverageGuard<'a> {
+    /// The coverage that will be restored.
+    pub fn previous(&self) -> Option<u16> {
+        self.previous
+    }
+}
+
+impl<'a> Drop for CoverageGuard<'a> {
+    fn drop(&mut self) {
+        // setting a value that could be read can only fail if the socket is broken
+        let _ = self.socket.set_send_checksum_coverage(self.previous);
+    }
+}
+
+
+/// Which metadata [`recv_msg()`](struct.UdpLiteSocket.html#method.recv_msg)
+/// should receive together with a datagram.
+///
+/// Everything is disabled by default.
+///
+/// # Examples
+///
+/// ```
+/// let options = udplite::RecvOptions::new().ttl(true).pktinfo(true);
+/// ```
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Debug)]
+pub struct RecvOptions {
+    ttl: bool,
+    timestamp: bool,
+    pktinfo: bool,
+    tos: bool,
+    orig_dst: bool,
+}
+
+impl RecvOptions {
+    /// Create options where nothing is enabled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Receive the TTL (IPv4) or hop limit (IPv6) of datagrams.
+    pub fn ttl(mut self,  enabled: bool) -> Self {
+        self.ttl = enabled;
+        self
+    }
+
+    /// Receive when datagrams were received by the OS. (`SO_TIMESTAMP`)
+    pub fn timestamp(mut self,  enabled: bool) -> Self {
+        self.timestamp = enabled;
+        self
+    }
+
+    /// Receive the destination address of datagrams and the index of
+    /// the interface they arrived on.
+    pub fn pktinfo(mut self,  enabled: bool) -> Self {
+        self.pktinfo = enabled;
+        self
+    }
+
+    /// Receive the TOS byte (IPv4) or traffic class (IPv6) of datagrams.
+    pub fn tos(mut self,  enabled: bool) -> Self {
+        self.tos = enabled;
+        self
+    }
+
+    /// Receive the original destination address and port of datagrams,
+    /// from before they were redirected by a firewall rule such as
+    /// `REDIRECT` or `TPROXY`.
+    pub fn orig_dst(mut self,  enabled: bool) -> Self {
+        self.orig_dst = enabled;
+        self
+    }
+}
+
+/// A datagram received by [`recv_msg()`](struct.UdpLiteSocket.html#method.recv_msg)
+/// and its metadata.
+///
+/// Metadata that wasn't requested with [`RecvOptions`](struct.RecvOptions.html),
+/// or that the OS didn't provide, is `None`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[non_exhaustive]
+pub struct RecvInfo {
+    /// The length of the datagram.
+    pub len: usize,
+    /// The address the datagram was sent from.
+    pub src_addr: SocketAddr,
+    /// The TTL (IPv4) or hop limit (IPv6) of the datagram.
+    pub ttl: Option<u8>,
+    /// When the OS received the datagram.
+    ///
+    /// This is from `SO_TIMESTAMP`, or from `SO_TIMESTAMPING` if software
+    /// receive timestamps are enabled with
+    /// [`set_timestamping()`](struct.UdpLiteSocket.html#method.set_timestamping).
+    pub timestamp: Option<SystemTime>,
+    /// When the network card received the datagram, if hardware receive
+    /// timestamps are enabled with
+    /// [`set_timestamping()`](struct.UdpLiteSocket.html#method.set_timestamping)
+    /// and supported by it.
+    ///
+    /// This is the raw hardware time, which is in the clock of the network
+    /// card and not necessarily synchronized with the system clock.
+    /// It's only available on Linux.
+    pub hardware_timestamp: Option<SystemTime>,
+    /// The address the datagram was sent to.
+    pub dest: Option<IpAddr>,
+    /// The index of the interface the datagram arrived on.
+    ///
+    /// This is not available for IPv4 on FreeBSD.
+    pub ifindex: Option<u32>,
+    /// The TOS byte (IPv4) or traffic class (IPv6) of the datagram.
+    pub tos: Option<u8>,
+    /// How many datagrams the socket has dropped since it was created,
+    /// if enabled with
+    /// [`set_recv_overflow_counter()`](struct.UdpLiteSocket.html#method.set_recv_overflow_counter).
+    ///
+    /// Linux omits it while the counter is zero, and it's only available
+    /// on Linux.
+    pub dropped: Option<u32>,
+    /// The address and port the datagram was originally sent to,
+    /// before any redirection by the firewall.
+    ///
+    /// Without redirection this is the address of the socket,
+    /// but with the specific IP if the socket is bound to an unspecified
+    /// address.
+    pub orig_dst: Option<SocketAddr>,
+}
+
+/// Declarative socket options for
+/// [`UdpLiteSocket::from_config()`](struct.UdpLiteSocket.html#method.from_config),
+/// which can be read from configuration files with serde.
+///
+/// Missing fields get their default value, which is the default of the OS
+/// for options; only `bind_addr` is normally needed.
+///
+/// This type requires the `serde` feature.
+///
+/// # Examples
+///
+/// ```
+/// use udplite::{UdpLiteConfig, UdpLiteSocket};
+///
+/// let config = UdpLiteConfig {
+///     bind_addr: "127.0.0.1:0".parse().unwrap(),
+///     send_coverage: Some(0),
+///     ..UdpLiteConfig::default()
+/// };
+/// let socket = UdpLiteSocket::from_config(&config).expect("create configured socket");
+/// assert_eq!(socket.send_checksum_coverage().unwrap(), Some(0));
+/// ```
+#[cfg(feature="serde")]
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UdpLiteConfig {
+    /// The address and port to bind to. Defaults to `0.0.0.0:0`.
+    pub bind_addr: SocketAddr,
+    /// Checksum coverage of sent datagrams, in bytes of payload.
+    /// `None` leaves the OS default, which covers entire datagrams.
+    pub send_coverage: Option<u16>,
+    /// Minimum checksum coverage of received datagrams, in bytes of payload.
+    /// `None` leaves the OS default, which is no filter on Linux.
+    /// (Unlike for
+    /// [`set_recv_checksum_coverage_filter()`](struct.UdpLiteSocket.html#method.set_recv_checksum_coverage_filter),
+    /// it doesn't mean requiring entire datagrams to be covered.
+    /// Call that method after creating the socket for that.)
+    pub recv_coverage_filter: Option<u16>,
+    /// Size of the receive buffer (`SO_RCVBUF`), or the OS default.
+    pub recv_buffer_size: Option<usize>,
+    /// Size of the send buffer (`SO_SNDBUF`), or the OS default.
+    pub send_buffer_size: Option<usize>,
+    /// Set `SO_REUSEADDR` before binding.
+    pub reuse_address: bool,
+    /// Set `SO_REUSEPORT` before binding.
+    pub reuse_port: bool,
+    /// Create the socket in non-blocking mode.
+    pub nonblocking: bool,
+}
+
+#[cfg(feature="serde")]
+impl Default for UdpLiteConfig {
+    fn default() -> Self {
+        UdpLiteConfig {
+            bind_addr: SocketAddr::from(([0, 0, 0, 0], 0)),
+            send_coverage: None,
+            recv_coverage_filter: None,
+            recv_buffer_size: None,
+            send_buffer_size: None,
+            reuse_address: false,
+            reuse_port: false,
+            nonblocking: false,
+        }
+    }
+}
+
+/// The error returned by
+/// [`send_many()`](struct.UdpLiteSocket.html#method.send_many) and
+/// [`send_to_many()`](struct.UdpLiteSocket.html#method.send_to_many):
+/// which datagram couldn't be sent, and why.
+///
+/// All datagrams before `index` were sent, and none after it.
+/// Converting it into an `io::Error` keeps the `ErrorKind` of `error`.
+#[derive(Debug)]
+pub struct BatchSendError {
+    /// The position of the datagram that couldn't be sent,
+    /// which is also the number of datagrams that were sent.
+    pub index: usize,
+    /// The error from sending it.
+    pub error: io::Error,
+}
+
+impl fmt::Display for BatchSendError {
+    fn fmt(&self,  fmtr: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmtr, "sending datagram {} failed: {}", self.index, self.error)
+    }
+}
+
+impl error::Error for BatchSendError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl From<BatchSendError> for io::Error {
+    fn from(error: BatchSendError) -> io::Error {
+        io::Error::new(error.error.kind(), error)
+    }
+}
+
+
+
+/// A receive checksum coverage filter that is probably not what was intended,
+/// found by [`filter_sanity_check()`](struct.UdpLiteSocket.html#method.filter_sanity_check).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[non_exhaustive]
+pub enum FilterWarning {
+    /// The filter is longer than the biggest possible payload for the
+    /// address family of the socket, so no partially covered datagram can
+    /// ever pass it.
+    ///
+    /// On Linux this makes it the same as `None`, and on FreeBSD
+    /// only entirely covered datagrams with exactly that length could match.
+    LongerThanAnyPayload {
+        /// The filter, in bytes of payload.
+        filter: u16,
+        /// The maximum payload of a datagram for the address family.
+        max_payload: u16,
+    },
+    /// The filter would discard the partially covered datagrams this socket
+    /// sends itself, so a peer configured the same way can't reach it.
+    ///
+    /// On Linux this means the filter is higher than the send coverage,
+    /// and on FreeBSD (where the filter must
+    /// [match exactly](fn.recv_filter_is_exact_match.html)) that they differ.
+    MismatchesSendCoverage {
+        /// The filter, in bytes of payload.
+        filter: u16,
+        /// The checksum coverage of sent datagrams.
+        send: Option<u16>,
+    },
+}
+
+impl fmt::Display for FilterWarning {
+    fn fmt(&self,  fmtr: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FilterWarning::LongerThanAnyPayload { filter, max_payload } => write!(fmtr,
+                "receive coverage filter {} is longer than any payload ({} bytes)",
+                filter, max_payload
+            ),
+            FilterWarning::MismatchesSendCoverage { filter, send: Some(send) } => write!(fmtr,
+                "receive coverage filter {} discards datagrams with send coverage {}",
+                filter, send
+            ),
+            FilterWarning::MismatchesSendCoverage { filter, send: None } => write!(fmtr,
+                "receive coverage filter {} discards entirely covered datagrams",
+                filter
+            ),
+        }
+    }
+}
+
+
+
+/// Either a UDP-Lite socket, or a plain UDP socket if the OS doesn't support UDP-Lite.
+///
+/// Methods common to both are available through `Deref` to `UdpSocket`,
+/// and the checksum coverage methods behave as if the UDP socket were a
+/// UDP-Lite socket which always covers entire datagrams.
+#[derive(Debug)]
+pub enum EitherSocket {
+    Lite(UdpLiteSocket),
+    Udp(UdpSocket),
+}
+
+impl EitherSocket {
+    /// Create a blocking UDP-Lite socket if [supported](fn.is_supported.html),
+    /// or a UDP socket otherwise.
+    pub fn bind_preferring_lite<A: ToSocketAddrs>(addrs: A) -> Result<Self, io::Error> {
+        if is_supported() {
+            UdpLiteSocket::bind(addrs).map(EitherSocket::Lite)
+        } else {
+            UdpSocket::bind(addrs).map(EitherSocket::Udp)
+        }
+    }
+
+    /// Check whether this is a UDP-Lite socket.
+    pub fn is_lite(&self) -> bool {
+        match self {
+            EitherSocket::Lite(_) => true,
+            EitherSocket::Udp(_) => false,
+        }
+    }
+
+    /// Change how many bytes of the payload of sent datagrams are covered by checksum.
+    ///
+    /// # Errors
+    ///
+    /// UDP sockets can only cover entire datagrams, so anything but `None`
+    /// fails for them.
+    pub fn set_send_checksum_coverage(&self,  coverage: Option<u16>)
+    -> Result<(), io::Error> {
+        match (self, coverage) {
+            (EitherSocket::Lite(lite), _) => lite.set_send_checksum_coverage(coverage),
+            (EitherSocket::Udp(_), None) => Ok(()),
+            (EitherSocket::Udp(_), Some(_)) => Err(io::Error::new(
+                Other,
+                "UDP doesn't support partial checksum coverage"
+            )),
+        }
+    }
+
+    /// Get how many bytes of the payload of sent datagrams are covered by checksum.
+    ///
+    /// Always `None` for UDP sockets.
+    pub fn send_checksum_coverage(&self) -> Result<Option<u16>, io::Error> {
+        match self {
+            EitherSocket::Lite(lite) => lite.send_checksum_coverage(),
+            EitherSocket::Udp(_) => Ok(None),
+        }
+    }
+
+    /// Set the required checksum coverage of payloads of received datagrams.
+    ///
+    /// This does nothing for UDP sockets, as datagrams received by them are
+    /// always covered entirely.
+    pub fn set_recv_checksum_coverage_filter(&self,  coverage: Option<u16>)
+    -> Result<(), io::Error> {
+        match self {
+            EitherSocket::Lite(lite) => lite.set_recv_checksum_coverage_filter(coverage),
+            EitherSocket::Udp(_) => Ok(()),
+        }
+    }
+
+    /// Get the required checksum coverage of payloads of received datagrams.
+    ///
+    /// Always `None` for UDP sockets.
+    pub fn recv_checksum_coverage_filter(&self) -> Result<Option<u16>, io::Error> {
+        match self {
+            EitherSocket::Lite(lite) => lite.recv_checksum_coverage_filter(),
+            EitherSocket::Udp(_) => Ok(None),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl AsRawFd for EitherSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        match self {
+            EitherSocket::Lite(lite) => lite.as_raw_fd(),
+            EitherSocket::Udp(udp) => udp.as_raw_fd(),
+        }
+    }
+}
+#[cfg(unix)]
+impl IntoRawFd for EitherSocket {
+    fn into_raw_fd(self) -> RawFd {
+        match self {
+            EitherSocket::Lite(lite) => lite.into_raw_fd(),
+            EitherSocket::Udp(udp) => udp.into_raw_fd(),
+        }
+    }
+}
+
+impl Deref for EitherSocket {
+    type Target = UdpSocket;
+    fn deref(&self) -> &UdpSocket {
+        match self {
+            EitherSocket::Lite(lite) => lite,
+            EitherSocket::Udp(udp) => udp,
+        }
+    }
+}
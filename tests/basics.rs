@@ -3,7 +3,8 @@ extern crate libc;
 
 use std::net::{Ipv4Addr, Ipv6Addr};
 use std::io::ErrorKind;
-use udplite::UdpLiteSocket;
+use std::os::unix::io::FromRawFd;
+use udplite::{UdpLiteSocket, AddressFamily};
 
 #[test]
 fn create_ipv4_socket() {
@@ -17,6 +18,95 @@ fn create_ipv6_socket() {
         .expect("create IPv6 UDP-Lite socket (bind to [::]:0)");
 }
 
+#[test]
+fn domain_of_bound_sockets() {
+    let ipv4 = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create IPv4 UDP-Lite socket (bind to 127.0.0.1:0)");
+    assert_eq!(ipv4.domain().expect("get domain of IPv4 socket"), AddressFamily::Ipv4);
+    let ipv6 = UdpLiteSocket::bind((Ipv6Addr::LOCALHOST, 0))
+        .expect("create IPv6 UDP-Lite socket (bind to [::1]:0)");
+    assert_eq!(ipv6.domain().expect("get domain of IPv6 socket"), AddressFamily::Ipv6);
+}
+
+#[test]
+fn domain_of_unbound_socket() {
+    // libc doesn't have IPPROTO_UDPLITE for Android
+    let fd = unsafe { libc::socket(libc::AF_INET6, libc::SOCK_DGRAM, 136) };
+    assert_ne!(fd, -1, "create unbound UDP-Lite socket");
+    let socket = unsafe { UdpLiteSocket::from_raw_fd(fd) };
+    assert_eq!(socket.domain().expect("get domain of unbound socket"), AddressFamily::Ipv6);
+}
+
+#[test]
+fn bound_and_connected() {
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 136) };
+    assert_ne!(fd, -1, "create unbound UDP-Lite socket");
+    let unbound = unsafe { UdpLiteSocket::from_raw_fd(fd) };
+    assert!(!unbound.is_bound());
+    assert!(!unbound.is_connected());
+
+    let a = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    let b = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create another socket bound to 127.0.0.1:0");
+    assert!(a.is_bound());
+    assert!(!a.is_connected());
+    a.connect(b.local_addr().unwrap()).expect("connect");
+    assert!(a.is_connected());
+    assert!(!b.is_connected());
+}
+
+#[test]
+fn bind_addr() {
+    let addr = std::net::SocketAddr::from((Ipv6Addr::LOCALHOST, 0));
+    let socket = UdpLiteSocket::bind_addr(addr).expect("create UDP-Lite socket bound to [::1]:0");
+    assert_eq!(socket.local_addr().expect("get local addr").ip(), addr.ip());
+    assert!(!socket.nonblocking().expect("get nonblocking"), "the socket is blocking");
+    let occupied = socket.local_addr().unwrap();
+    let error = UdpLiteSocket::bind_addr(occupied).expect_err("port is in use");
+    assert_eq!(error.kind(), ErrorKind::AddrInUse);
+}
+
+#[test]
+fn bind_prefer() {
+    use std::net::SocketAddr;
+    let addrs = [
+        SocketAddr::from((Ipv6Addr::LOCALHOST, 0)),
+        SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+    ];
+    let socket = UdpLiteSocket::bind_prefer(&addrs[..], Some(AddressFamily::Ipv4))
+        .expect("create UDP-Lite socket preferring IPv4");
+    assert_eq!(socket.domain().expect("get domain"), AddressFamily::Ipv4);
+    let socket = UdpLiteSocket::bind_prefer(&addrs[..], None)
+        .expect("create UDP-Lite socket without preference");
+    assert_eq!(socket.domain().expect("get domain"), AddressFamily::Ipv6, "resolver order");
+
+    let occupier = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let fallback = [occupier.local_addr().unwrap(), SocketAddr::from((Ipv6Addr::LOCALHOST, 0))];
+    let socket = UdpLiteSocket::bind_prefer(&fallback[..], Some(AddressFamily::Ipv4))
+        .expect("fall back to IPv6");
+    assert_eq!(socket.domain().expect("get domain"), AddressFamily::Ipv6);
+}
+
+#[test]
+fn bind_v4_and_v6() {
+    use std::net::SocketAddr;
+    let addrs = [
+        SocketAddr::from((Ipv6Addr::LOCALHOST, 0)),
+        SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+    ];
+    let v4 = UdpLiteSocket::bind_v4(&addrs[..]).expect("create IPv4 socket");
+    assert_eq!(v4.domain().expect("get domain"), AddressFamily::Ipv4);
+    let v6 = UdpLiteSocket::bind_v6(&addrs[..]).expect("create IPv6 socket");
+    assert_eq!(v6.domain().expect("get domain"), AddressFamily::Ipv6);
+
+    let error = UdpLiteSocket::bind_v6((Ipv4Addr::LOCALHOST, 0)).expect_err("no IPv6 address");
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    assert!(error.to_string().contains("IPv6"), "{}", error);
+    let error = UdpLiteSocket::bind_v4("[::1]:0").expect_err("no IPv4 address");
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
 #[test]
 fn create_nonblocking_socket() {
     let socket = UdpLiteSocket::bind_nonblocking((Ipv4Addr::new(0, 0, 0, 0), 0))
@@ -57,26 +147,39 @@ fn send_connected_ipv6() {
     let a_addr = a.local_addr().expect("get local addr of socket a");
     let b_addr = b.local_addr().expect("get local addr of socket b");
     a.connect(b_addr)
-        .expect(&format!("connect socket a to addr of socket b ({})", b_addr));
+        .unwrap_or_else(|_| panic!("connect socket a to addr of socket b ({})", b_addr));
     b.connect(a_addr)
-        .expect(&format!("connect socket b to addr of socket a ({})", a_addr));
+        .unwrap_or_else(|_| panic!("connect socket b to addr of socket a ({})", a_addr));
 
     let msg = "Hello";
     let sent_bytes = a.send(msg.as_bytes())
-        .expect(&format!(
+        .unwrap_or_else(|_| panic!(
                 "Send from socket a ({:?}) to addr of socket b ({})",
                 a, b_addr
         ));
     assert_eq!(sent_bytes, msg.len());
     let mut buf = [0u8; 20];
     let received_bytes = b.recv(&mut buf)
-        .expect(&format!(
+        .unwrap_or_else(|_| panic!(
                 "Receive from socket b ({:?}) connected to addr of socket a ({})",
                 b, a_addr
         ));
     assert_eq!(&buf[..received_bytes], msg.as_bytes());
 }
 
+#[test]
+fn set_get_both_cscov() {
+    let socket = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create IPv4 UDP-Lite socket (bind to 127.0.0.1:0)");
+    assert_eq!(socket.checksum_coverage().expect("get both cscovs"), (None, None));
+    socket.set_checksum_coverage(Some(12), Some(4)).expect("set both cscovs");
+    assert_eq!(socket.checksum_coverage().expect("get both cscovs"), (Some(12), Some(4)));
+    assert_eq!(socket.send_checksum_coverage().expect("get send cscov"), Some(12));
+    assert_eq!(socket.recv_checksum_coverage_filter().expect("get recv cscov"), Some(4));
+    socket.set_checksum_coverage(None, Some(0)).expect("set both cscovs");
+    assert_eq!(socket.checksum_coverage().expect("get both cscovs"), (None, Some(0)));
+}
+
 #[test]
 fn set_get_recv_cscov() {
     let socket = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
@@ -109,6 +212,109 @@ fn set_get_send_cscov() {
     assert_eq!(socket.send_checksum_coverage().expect("get send cscov"), Some(!0-8));
 }
 
+#[test]
+fn filter_sanity_check() {
+    use udplite::FilterWarning;
+    let v4 = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    assert_eq!(v4.filter_sanity_check().expect("check default filter"), None);
+    v4.set_checksum_coverage(Some(8), Some(8)).expect("set symmetric cscov");
+    assert_eq!(v4.filter_sanity_check().expect("check matching filter"), None);
+
+    v4.set_recv_checksum_coverage_filter(Some(12)).expect("set higher recv cscov filter");
+    let expected = FilterWarning::MismatchesSendCoverage { filter: 12,  send: Some(8) };
+    assert_eq!(v4.filter_sanity_check().expect("check higher filter"), Some(expected));
+    assert!(expected.to_string().contains("12"), "{}", expected);
+
+    v4.set_recv_checksum_coverage_filter(Some(0xffff - 8)).expect("set huge recv cscov filter");
+    let warning = v4.filter_sanity_check().expect("check huge filter");
+    assert_eq!(warning, Some(FilterWarning::LongerThanAnyPayload {
+        filter: 0xffff - 8,
+        max_payload: 0xffff - 28,
+    }));
+
+    let v6 = UdpLiteSocket::bind((Ipv6Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to [::1]:0");
+    v6.set_checksum_coverage(Some(0xffff - 8), Some(0xffff - 8)).expect("set max cscov");
+    assert_eq!(v6.filter_sanity_check().expect("check max IPv6 filter"), None);
+}
+
+#[test]
+fn swap_send_cscov() {
+    let socket = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create IPv4 UDP-Lite socket (bind to 127.0.0.1:0)");
+    assert_eq!(socket.swap_send_checksum_coverage(Some(8)).expect("swap from default"), None);
+    assert_eq!(socket.swap_send_checksum_coverage(Some(0)).expect("swap again"), Some(8));
+    assert_eq!(socket.swap_send_checksum_coverage(None).expect("restore default"), Some(0));
+    assert_eq!(socket.send_checksum_coverage().expect("get send cscov"), None);
+}
+
+#[test]
+fn with_send_coverage_restores() {
+    let socket = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create IPv4 UDP-Lite socket (bind to 127.0.0.1:0)");
+    socket.set_send_checksum_coverage(Some(4)).expect("set send cscov");
+    {
+        let guard = socket.with_send_coverage(Some(8)).expect("temporarily change cscov");
+        assert_eq!(guard.previous(), Some(4));
+        assert_eq!(socket.send_checksum_coverage().expect("get send cscov"), Some(8));
+        let inner = socket.with_send_coverage(None).expect("nest guards");
+        assert_eq!(socket.send_checksum_coverage().expect("get send cscov"), None);
+        drop(inner);
+        assert_eq!(socket.send_checksum_coverage().expect("get send cscov"), Some(8));
+    }
+    assert_eq!(socket.send_checksum_coverage().expect("get send cscov"), Some(4));
+
+    let result = std::panic::catch_unwind(|| {
+        let _guard = socket.with_send_coverage(Some(0)).expect("temporarily change cscov");
+        panic!("restored anyway");
+    });
+    assert!(result.is_err());
+    assert_eq!(socket.send_checksum_coverage().expect("get send cscov"), Some(4));
+}
+
+#[test]
+fn self_test_partial_coverage() {
+    let socket = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    socket.set_send_checksum_coverage(Some(12)).expect("set send cscov");
+    assert!(socket.self_test_partial_coverage().expect("run self test"), "loopback works");
+    assert_eq!(socket.send_checksum_coverage().expect("get send cscov"), Some(12), "restored");
+
+    let unbound = UdpLiteSocket::new_unbound(AddressFamily::Ipv6).expect("create unbound socket");
+    assert!(unbound.self_test_partial_coverage().expect("run self test"), "uses loopback");
+    let port = unbound.local_addr().expect("get local addr").port();
+    assert_ne!(port, 0, "sending bound the socket");
+}
+
+#[test]
+fn recv_from_timed() {
+    use std::time::Instant;
+    let (a, b) = UdpLiteSocket::pair().expect("create connected pair");
+    let a_addr = a.local_addr().expect("get local addr of a");
+    let before = Instant::now();
+    a.send(b"tick").expect("send datagram");
+    let mut buf = [0u8; 10];
+    let (len, from, at) = b.recv_from_timed(&mut buf).expect("receive datagram");
+    assert_eq!((&buf[..len], from), (&b"tick"[..], a_addr));
+    assert!(at >= before && at <= Instant::now());
+}
+
+#[test]
+fn set_header_coverage() {
+    let socket = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create IPv4 UDP-Lite socket (bind to 127.0.0.1:0)");
+    socket.set_send_checksum_coverage(udplite::header_coverage(4))
+        .expect("Set send cscov to cover a header");
+    assert_eq!(socket.send_checksum_coverage().expect("get send cscov"), Some(4));
+    socket.set_send_checksum_coverage(udplite::header_coverage(!0))
+        .expect("Set send cscov to cover a too-long header");
+    assert_eq!(socket.send_checksum_coverage().expect("get send cscov"), None);
+    socket.set_send_checksum_coverage(udplite::header_coverage(!0-8))
+        .expect("Set send cscov to cover the longest partial header");
+    assert_eq!(socket.send_checksum_coverage().expect("get send cscov"), Some(!0-8));
+}
+
 #[test]
 fn try_clone_returns_udplite() {
     let socket = UdpLiteSocket::bind((Ipv4Addr::new(127, 0, 0, 1), 0))
@@ -117,3 +323,1467 @@ fn try_clone_returns_udplite() {
     clone.set_send_checksum_coverage(Some(100))
         .expect("change checksum coverage of cloned UDP-Lite socket");
 }
+
+#[test]
+fn clones_share_coverage() {
+    let socket = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket");
+    socket.set_send_checksum_coverage(Some(3)).expect("set send cscov");
+    let clone = socket.try_clone().expect("duplicate UDP-Lite socket");
+    assert_eq!(clone.send_checksum_coverage().expect("get send cscov of clone"), Some(3));
+
+    let reconfigured = socket.clone_with_coverage(Some(7), Some(5))
+        .expect("clone with different coverage");
+    assert_eq!(reconfigured.send_checksum_coverage().expect("get send cscov"), Some(7));
+    assert_eq!(reconfigured.recv_checksum_coverage_filter().expect("get recv cscov"), Some(5));
+    assert_eq!(
+        socket.send_checksum_coverage().expect("get send cscov of original"),
+        Some(7),
+        "the original is changed too, because they're the same socket"
+    );
+    assert_eq!(socket.recv_checksum_coverage_filter().expect("get recv cscov"), Some(5));
+    assert_eq!(clone.send_checksum_coverage().expect("get send cscov of clone"), Some(7));
+}
+
+#[test]
+fn display() {
+    let socket = UdpLiteSocket::bind((Ipv6Addr::LOCALHOST, 0))
+        .expect("create IPv6 UDP-Lite socket (bind to [::1]:0)");
+    let port = socket.local_addr().expect("get local addr").port();
+    socket.set_recv_checksum_coverage_filter(Some(8)).expect("set recv cscov filter");
+    assert_eq!(
+        socket.to_string(),
+        format!("UDP-Lite [::1]:{} (send cscov=full, recv filter=8)", port)
+    );
+}
+
+#[test]
+fn debug_verbose() {
+    use std::os::unix::io::AsRawFd;
+    let socket = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    let addr = socket.local_addr().expect("get local addr");
+    socket.set_send_checksum_coverage(Some(4)).expect("set send cscov");
+    assert_eq!(
+        format!("{:?}", socket.debug_verbose()),
+        format!(
+            "UdpLiteSocket {{ addr: {:?}, fd: {}, send_cscov: Some(4), recv_cscov: None }}",
+            addr,
+            socket.as_raw_fd()
+        )
+    );
+    socket.connect(addr).expect("connect to itself");
+    let verbose = format!("{:#?}", socket.debug_verbose());
+    assert!(verbose.contains(&format!("peer: {:?}", addr)), "shows peer address: {}", verbose);
+}
+
+#[test]
+fn incoming() {
+    let a = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    let b = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create another socket bound to 127.0.0.1:0");
+    let a_addr = a.local_addr().expect("get local addr of socket a");
+    let b_addr = b.local_addr().expect("get local addr of socket b");
+    a.send_to(b"first", b_addr).expect("send first datagram");
+    a.send_to(b"second", b_addr).expect("send second datagram");
+    b.set_nonblocking(true).expect("make recv fail instead of hanging");
+    let mut incoming = b.incoming().max_len(5);
+    assert_eq!(incoming.next().unwrap().expect("receive first"), (b"first".to_vec(), a_addr));
+    assert_eq!(incoming.next().unwrap().expect("receive second"), (b"secon".to_vec(), a_addr));
+    let error = incoming.next().unwrap().expect_err("no more datagrams");
+    assert_eq!(error.kind(), ErrorKind::WouldBlock);
+
+    a.send_to(b"short", b_addr).expect("send third datagram");
+    let (datagram, _) = b.incoming().next().unwrap().expect("receive with default max_len");
+    assert_eq!(datagram, b"short");
+    assert!(datagram.capacity() < 0xffff, "the receive buffer isn't returned");
+}
+
+#[test]
+fn try_recv_doesnt_block() {
+    let a = UdpLiteSocket::bind((Ipv6Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to [::1]:0");
+    let b = UdpLiteSocket::bind((Ipv6Addr::LOCALHOST, 0))
+        .expect("create another socket bound to [::1]:0");
+    let a_addr = a.local_addr().expect("get local addr of socket a");
+    let b_addr = b.local_addr().expect("get local addr of socket b");
+    let mut buf = [0u8; 10];
+    assert_eq!(b.try_recv_from(&mut buf).expect("try_recv_from() on empty socket"), None);
+    a.send_to(b"hi", b_addr).expect("send datagram");
+    assert_eq!(b.try_recv_from(&mut buf).expect("try_recv_from()"), Some((2, a_addr)));
+    assert!(!b.nonblocking().expect("get nonblocking"), "try_recv_from() doesn't change mode");
+
+    b.connect(a_addr).expect("connect socket b to socket a");
+    assert_eq!(b.try_recv(&mut buf).expect("try_recv() on empty socket"), None);
+    a.send_to(b"hello", b_addr).expect("send datagram");
+    assert_eq!(b.try_recv(&mut buf).expect("try_recv()"), Some(5));
+    assert_eq!(&buf[..5], b"hello");
+}
+
+#[test]
+fn send_many_and_all() {
+    let a = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    let b = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create another socket bound to 127.0.0.1:0");
+    let c = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create a third socket bound to 127.0.0.1:0");
+    let b_addr = b.local_addr().expect("get local addr of socket b");
+    let c_addr = c.local_addr().expect("get local addr of socket c");
+    b.set_nonblocking(true).expect("make recv fail instead of hanging");
+    c.set_nonblocking(true).expect("make recv fail instead of hanging");
+
+    let msgs = [(b_addr, &b"one"[..]), (c_addr, &b"two"[..]), (b_addr, &b"three"[..])];
+    assert_eq!(a.send_many(&msgs).expect("send multiple datagrams"), 3);
+    assert_eq!(a.send_many(&[]).expect("send no datagrams"), 0);
+    a.send_all_to(&msgs).expect("send all datagrams");
+
+    let mut buf = [0u8; 10];
+    for _ in 0..2 {
+        assert_eq!(b.recv(&mut buf).expect("receive first datagram for b"), 3);
+        assert_eq!(&buf[..3], b"one");
+        assert_eq!(b.recv(&mut buf).expect("receive second datagram for b"), 5);
+        assert_eq!(&buf[..5], b"three");
+        assert_eq!(c.recv(&mut buf).expect("receive datagram for c"), 3);
+        assert_eq!(&buf[..3], b"two");
+    }
+}
+
+#[test]
+fn batch_size_limit() {
+    let a = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    let a_addr = a.local_addr().expect("get local addr of socket a");
+    a.set_nonblocking(true).expect("make recv fail instead of hanging");
+    let max = udplite::max_batch_size();
+    assert!(max >= 1);
+
+    let too_many = vec![a_addr; max + 1];
+    let error = a.send_to_many(b"", &too_many).expect_err("more than the kernel accepts");
+    assert_eq!((error.index, error.error.kind()), (0, ErrorKind::InvalidInput));
+    let mut buf = [0u8; 1];
+    assert_eq!(a.recv(&mut buf).expect_err("nothing was sent").kind(), ErrorKind::WouldBlock);
+
+    let mut batch = udplite::BatchBuffer::new(max + 1, 1);
+    let error = a.recv_batch(&mut batch).err().expect("more than the kernel accepts");
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    let mut bufs = vec![[0u8; 1]; max + 1];
+    let mut bufs = bufs.iter_mut().map(|buf| &mut buf[..]).collect::<Vec<&mut[u8]>>();
+    let error = a.recv_many(&mut bufs).expect_err("more than the kernel accepts");
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+
+    let msgs = vec![(a_addr, &b""[..]); max + 1];
+    a.send_all_to(&msgs).expect("send_all_to() splits up the datagrams");
+}
+
+#[test]
+fn convert_sockaddr() {
+    use std::net::{SocketAddr, SocketAddrV6};
+    use std::mem;
+    use udplite::sockaddr_to_rust_addr;
+
+    let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    {
+        let sin6 = unsafe {
+            &mut*(&mut storage as *mut libc::sockaddr_storage as *mut libc::sockaddr_in6)
+        };
+        sin6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+        sin6.sin6_port = 1234u16.to_be();
+        sin6.sin6_flowinfo = 5;
+        sin6.sin6_scope_id = 6;
+        sin6.sin6_addr.s6_addr = Ipv6Addr::LOCALHOST.octets();
+    }
+    let len = mem::size_of::<libc::sockaddr_in6>() as libc::socklen_t;
+    assert_eq!(
+        sockaddr_to_rust_addr(&storage, len).expect("convert IPv6 address"),
+        SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::LOCALHOST, 1234, 5, 6))
+    );
+    sockaddr_to_rust_addr(&storage, len-1).expect_err("too short IPv6 address");
+
+    {
+        let sin = unsafe {
+            &mut*(&mut storage as *mut libc::sockaddr_storage as *mut libc::sockaddr_in)
+        };
+        sin.sin_family = libc::AF_INET as libc::sa_family_t;
+        sin.sin_port = 80u16.to_be();
+        sin.sin_addr.s_addr = u32::from(Ipv4Addr::new(192, 0, 2, 1)).to_be();
+    }
+    let len = mem::size_of::<libc::sockaddr_in>() as libc::socklen_t;
+    assert_eq!(
+        sockaddr_to_rust_addr(&storage, len).expect("convert IPv4 address"),
+        SocketAddr::from((Ipv4Addr::new(192, 0, 2, 1), 80))
+    );
+
+    storage.ss_family = libc::AF_UNIX as libc::sa_family_t;
+    let error = sockaddr_to_rust_addr(&storage, len).expect_err("unix address");
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+}
+
+#[test]
+fn recv_tos() {
+    use std::mem;
+    use std::os::unix::io::AsRawFd;
+
+    fn set_sent_tos(socket: &UdpLiteSocket,  level: libc::c_int,  name: libc::c_int) {
+        let tos: libc::c_int = 0x20;
+        let ret = unsafe {
+            libc::setsockopt(
+                socket.as_raw_fd(),
+                level,
+                name,
+                &tos as *const libc::c_int as *const libc::c_void,
+                mem::size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        assert_eq!(ret, 0, "set TOS / traffic class of sent packets");
+    }
+
+    let a = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    let b = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create another socket bound to 127.0.0.1:0");
+    let a_addr = a.local_addr().expect("get local addr of socket a");
+    let b_addr = b.local_addr().expect("get local addr of socket b");
+    set_sent_tos(&a, libc::IPPROTO_IP, libc::IP_TOS);
+    b.set_nonblocking(true).expect("make recv fail instead of hanging");
+    let mut buf = [0u8; 10];
+
+    assert!(!b.recv_tos().expect("get whether TOS is received"));
+    a.send_to(b"without", b_addr).expect("send datagram");
+    assert_eq!(b.recv_from_with_tos(&mut buf).expect("receive"), (7, a_addr, None));
+    b.set_recv_tos(true).expect("enable receiving TOS");
+    assert!(b.recv_tos().expect("get whether TOS is received"));
+    a.send_to(b"with", b_addr).expect("send datagram");
+    assert_eq!(b.recv_from_with_tos(&mut buf).expect("receive"), (4, a_addr, Some(0x20)));
+
+    let a = UdpLiteSocket::bind((Ipv6Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to [::1]:0");
+    let b = UdpLiteSocket::bind((Ipv6Addr::LOCALHOST, 0))
+        .expect("create another socket bound to [::1]:0");
+    let a_addr = a.local_addr().expect("get local addr of socket a");
+    let b_addr = b.local_addr().expect("get local addr of socket b");
+    set_sent_tos(&a, libc::IPPROTO_IPV6, libc::IPV6_TCLASS);
+    b.set_nonblocking(true).expect("make recv fail instead of hanging");
+    b.set_recv_tos(true).expect("enable receiving traffic class");
+    a.send_to(b"with", b_addr).expect("send datagram");
+    assert_eq!(b.recv_from_with_tos(&mut buf).expect("receive"), (4, a_addr, Some(0x20)));
+}
+
+#[test]
+fn recv_filter_with_higher_coverage() {
+    let a = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    let b = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create another socket bound to 127.0.0.1:0");
+    let b_addr = b.local_addr().expect("get local addr of socket b");
+    a.set_send_checksum_coverage(Some(10)).expect("set send cscov");
+    b.set_recv_checksum_coverage_filter(Some(5)).expect("set recv cscov filter");
+    b.set_nonblocking(true).expect("make recv fail instead of hanging");
+
+    a.send_to(b"partially covered", b_addr).expect("send datagram");
+    let mut buf = [0u8; 20];
+    let result = b.recv(&mut buf);
+    if udplite::recv_filter_is_exact_match() {
+        let error = result.expect_err("datagrams with higher coverage are discarded");
+        assert_eq!(error.kind(), ErrorKind::WouldBlock);
+    } else {
+        assert_eq!(result.expect("datagrams with higher coverage are received"), 17);
+    }
+}
+
+#[test]
+fn echo_with_partial_coverage() {
+    use std::time::Duration;
+    let server = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create server socket bound to 127.0.0.1:0");
+    server.set_checksum_coverage(Some(8), Some(8)).expect("set server cscov");
+    let server_addr = server.local_addr().unwrap();
+    let echoer = std::thread::spawn(move || {
+        let mut buf = [0u8; 100];
+        // stop after the empty datagram
+        loop {
+            let (len, from) = server.recv_from(&mut buf).expect("receive request");
+            server.send_to(&buf[..len], from).expect("echo datagram");
+            if len == 0 {
+                break;
+            }
+        }
+    });
+
+    let client = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create client socket bound to 127.0.0.1:0");
+    client.connect_with_coverage(server_addr, Some(8)).expect("connect to server");
+    client.set_recv_checksum_coverage_filter(Some(8)).expect("set client recv cscov filter");
+    let mut buf = [0u8; 100];
+    client.send(b"covered|not covered").expect("send request");
+    let echo = client.recv_from_timeout(&mut buf, Duration::from_secs(5)).expect("receive echo");
+    assert_eq!(echo, Some((19, server_addr)));
+    assert_eq!(&buf[..19], b"covered|not covered");
+
+    // the server discards datagrams with less coverage than its filter
+    client.set_send_checksum_coverage(Some(4)).expect("reduce send cscov");
+    client.send(b"coverage too low").expect("send request with too low coverage");
+    let echo = client.recv_from_timeout(&mut buf, Duration::from_millis(200)).expect("wait");
+    assert_eq!(echo, None, "the server doesn't receive datagrams with insufficient coverage");
+
+    // datagrams shorter than the filter are fully covered
+    client.send(&[]).expect("send empty datagram to stop the server");
+    echoer.join().expect("the server thread doesn't panic");
+}
+
+#[test]
+fn coverage_longer_than_datagram() {
+    let a = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    let b = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create another socket bound to 127.0.0.1:0");
+    let b_addr = b.local_addr().expect("get local addr of socket b");
+    a.set_send_checksum_coverage(Some(1000)).expect("set send cscov");
+    b.set_nonblocking(true).expect("make recv fail instead of hanging");
+    let mut buf = [0u8; 20];
+
+    // a short datagram is entirely covered
+    b.set_recv_checksum_coverage_filter(None).expect("require full coverage");
+    a.send_to(b"ten bytes!", b_addr).expect("send short datagram");
+    assert_eq!(b.recv(&mut buf).expect("entirely covered datagram is received"), 10);
+    assert_eq!(&buf[..10], b"ten bytes!");
+
+    b.set_recv_checksum_coverage_filter(Some(1000)).expect("set matching recv cscov filter");
+    a.send_to(b"ten bytes!", b_addr).expect("send short datagram");
+    let result = b.recv(&mut buf);
+    if udplite::recv_filter_is_exact_match() {
+        let error = result.expect_err("coverage of the datagram is lower than the filter");
+        assert_eq!(error.kind(), ErrorKind::WouldBlock);
+    } else {
+        assert_eq!(result.expect("entirely covered datagrams pass any filter"), 10);
+    }
+}
+
+#[test]
+fn empty_datagram_coverage() {
+    let a = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    let b = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create another socket bound to 127.0.0.1:0");
+    let a_addr = a.local_addr().expect("get local addr of socket a");
+    let b_addr = b.local_addr().expect("get local addr of socket b");
+    b.set_nonblocking(true).expect("make recv fail instead of hanging");
+    let mut buf = [0u8; 10];
+
+    for &send in &[None, Some(0), Some(4)] {
+        a.set_send_checksum_coverage(send).expect("set send cscov");
+        for &filter in &[None, Some(0), Some(4), Some(100)] {
+            b.set_recv_checksum_coverage_filter(filter).expect("set recv cscov filter");
+            a.send_keepalive_to(b_addr).expect("send empty datagram");
+            let result = b.recv_from(&mut buf);
+            if udplite::recv_filter_is_exact_match() && filter.map_or(false, |f| f > 0) {
+                let error = result.expect_err("only the header is covered");
+                assert_eq!(error.kind(), ErrorKind::WouldBlock);
+            } else {
+                let received = result.unwrap_or_else(|e| {
+                    panic!("send {:?} filter {:?}: empty datagram isn't received: {}", send, filter, e)
+                });
+                assert_eq!(received, (0, a_addr), "send {:?} filter {:?}", send, filter);
+            }
+        }
+    }
+}
+
+#[cfg(any(target_os="linux", target_os="android"))]
+#[test]
+fn recv_filter_none_and_zero() {
+    let a = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    let b = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create another socket bound to 127.0.0.1:0");
+    let b_addr = b.local_addr().expect("get local addr of socket b");
+    b.set_nonblocking(true).expect("make recv fail instead of hanging");
+    let mut buf = [0u8; 20];
+    let mut send_and_recv = |coverage| {
+        a.set_send_checksum_coverage(coverage).expect("set send cscov");
+        a.send_to(b"0123456789", b_addr).expect("send datagram");
+        match b.recv(&mut buf) {
+            Ok(len) => Some(len),
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => None,
+            Err(e) => panic!("receive failed: {}", e),
+        }
+    };
+
+    // default filter accepts everything, but reads as None
+    assert_eq!(b.recv_checksum_coverage_filter().expect("get recv cscov"), None);
+    assert_eq!(send_and_recv(Some(0)), Some(10), "default filter accepts partial coverage");
+    assert_eq!(send_and_recv(None), Some(10), "default filter accepts full coverage");
+
+    b.set_recv_checksum_coverage_filter(None).expect("require full coverage");
+    assert_eq!(send_and_recv(Some(0)), None, "None discards partial coverage");
+    assert_eq!(send_and_recv(Some(9)), None, "None discards almost full coverage");
+    assert_eq!(send_and_recv(Some(10)), Some(10), "None accepts coverage of entire payload");
+    assert_eq!(send_and_recv(None), Some(10), "None accepts full coverage");
+
+    b.set_recv_checksum_coverage_filter(Some(0)).expect("accept any coverage");
+    assert_eq!(send_and_recv(Some(0)), Some(10), "Some(0) accepts minimum coverage");
+    assert_eq!(send_and_recv(None), Some(10), "Some(0) accepts full coverage");
+
+    b.require_full_coverage().expect("require full coverage");
+    assert_eq!(b.recv_checksum_coverage_filter().expect("get recv cscov"), None);
+    assert_eq!(send_and_recv(Some(9)), None, "require_full_coverage() discards partial coverage");
+    assert_eq!(send_and_recv(None), Some(10), "require_full_coverage() accepts full coverage");
+    b.accept_any_coverage().expect("accept any coverage");
+    assert_eq!(b.recv_checksum_coverage_filter().expect("get recv cscov"), Some(0));
+    assert_eq!(send_and_recv(Some(0)), Some(10), "accept_any_coverage() accepts minimum coverage");
+    assert_eq!(send_and_recv(None), Some(10), "accept_any_coverage() accepts full coverage");
+}
+
+#[cfg(all(feature="raw_coverage", any(target_os="linux", target_os="android")))]
+#[test]
+fn recv_filter_with_raw_coverage_field() {
+    let a = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    let b = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create another socket bound to 127.0.0.1:0");
+    let a_addr = a.local_addr().expect("get local addr of socket a");
+    let b_addr = b.local_addr().expect("get local addr of socket b");
+    b.set_nonblocking(true).expect("make recv fail instead of hanging");
+    let mut buf = [0u8; 20];
+    let mut send_and_recv = |coverage_field| {
+        match a.send_raw_with_coverage_field(b"0123456789", coverage_field, b_addr) {
+            Ok(sent) => assert_eq!(sent, 10),
+            Err(ref e) if e.kind() == ErrorKind::PermissionDenied => return Err(()),
+            Err(e) => panic!("sending raw packet failed: {}", e),
+        }
+        match b.recv_from(&mut buf) {
+            Ok(received) => {
+                assert_eq!(received, (10, a_addr));
+                assert_eq!(&buf[..10], b"0123456789");
+                Ok(true)
+            }
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => Ok(false),
+            Err(e) => panic!("receive failed: {}", e),
+        }
+    };
+    if send_and_recv(0) == Err(()) {
+        return; // not root
+    }
+
+    b.set_recv_checksum_coverage_filter(Some(4)).expect("set recv cscov filter");
+    assert_eq!(send_and_recv(0), Ok(true), "entire datagram is covered");
+    assert_eq!(send_and_recv(8 + 4), Ok(true), "coverage equal to the filter");
+    assert_eq!(send_and_recv(8 + 6), Ok(true), "coverage above the filter");
+    assert_eq!(send_and_recv(8 + 3), Ok(false), "coverage below the filter");
+    assert_eq!(send_and_recv(8 + 10), Ok(true), "coverage of the entire datagram");
+    b.set_recv_checksum_coverage_filter(Some(0)).expect("accept any coverage");
+    assert_eq!(send_and_recv(8), Ok(true), "only the header is covered");
+    assert_eq!(send_and_recv(5), Ok(false), "coverage shorter than the header is invalid");
+    assert_eq!(send_and_recv(8 + 11), Ok(false), "coverage longer than the datagram is invalid");
+
+    let c = UdpLiteSocket::bind((Ipv6Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to [::1]:0");
+    let d = UdpLiteSocket::bind((Ipv6Addr::UNSPECIFIED, 0))
+        .expect("create UDP-Lite socket bound to [::]:0");
+    let c_addr = c.local_addr().expect("get local addr of socket c");
+    c.set_recv_checksum_coverage_filter(Some(2)).expect("set recv cscov filter");
+    c.set_nonblocking(true).expect("make recv fail instead of hanging");
+    d.send_raw_with_coverage_field(b"IPv6", 8 + 2, c_addr).expect("send raw IPv6 packet");
+    let d_port = d.local_addr().expect("get local addr of socket d").port();
+    let received = c.recv_from(&mut buf).expect("receive IPv6 packet");
+    assert_eq!(received, (4, (Ipv6Addr::LOCALHOST, d_port).into()));
+}
+
+#[test]
+fn send_to_many() {
+    let a = UdpLiteSocket::bind((Ipv6Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to [::1]:0");
+    let b = UdpLiteSocket::bind((Ipv6Addr::LOCALHOST, 0))
+        .expect("create another socket bound to [::1]:0");
+    let c = UdpLiteSocket::bind((Ipv6Addr::LOCALHOST, 0))
+        .expect("create a third socket bound to [::1]:0");
+    let a_addr = a.local_addr().expect("get local addr of socket a");
+    let b_addr = b.local_addr().expect("get local addr of socket b");
+    let c_addr = c.local_addr().expect("get local addr of socket c");
+    b.set_nonblocking(true).expect("make recv fail instead of hanging");
+    c.set_nonblocking(true).expect("make recv fail instead of hanging");
+
+    assert_eq!(a.send_to_many(b"everyone", &[b_addr, c_addr]).expect("send to both"), 2);
+    assert_eq!(a.send_to_many(b"no one", &[]).expect("send to none"), 0);
+    let mut buf = [0u8; 10];
+    assert_eq!(b.recv_from(&mut buf).expect("receive datagram for b"), (8, a_addr));
+    assert_eq!(&buf[..8], b"everyone");
+    assert_eq!(c.recv_from(&mut buf).expect("receive datagram for c"), (8, a_addr));
+    assert_eq!(&buf[..8], b"everyone");
+    b.recv_from(&mut buf).expect_err("only one datagram for b");
+}
+
+#[test]
+fn send_many_reports_failing_index() {
+    let a = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    let b = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create another socket bound to 127.0.0.1:0");
+    let b_addr = b.local_addr().expect("get local addr of socket b");
+    let unsendable = (Ipv6Addr::LOCALHOST, b_addr.port()).into();
+    b.set_nonblocking(true).expect("make recv fail instead of hanging");
+
+    let error = a.send_to_many(b"fan-out", &[b_addr, b_addr, unsendable, b_addr])
+        .expect_err("cannot send to IPv6 address");
+    assert_eq!(error.index, 2, "the first two were sent");
+    assert_eq!(error.error.kind(), ErrorKind::InvalidInput);
+    let as_io: std::io::Error = error.into();
+    assert_eq!(as_io.kind(), ErrorKind::InvalidInput, "conversion keeps the kind");
+    let mut buf = [0u8; 10];
+    b.recv(&mut buf).expect("receive first datagram");
+    b.recv(&mut buf).expect("receive second datagram");
+    b.recv(&mut buf).expect_err("the rest weren't sent");
+
+    let msgs = [(b_addr, &b"ok"[..]), (unsendable, &b"bad"[..])];
+    let error = a.send_many(&msgs).expect_err("cannot send to IPv6 address");
+    assert_eq!(error.index, 1);
+    assert!(error.to_string().starts_with("sending datagram 1 failed: "), "{}", error);
+}
+
+#[test]
+fn recv_send_with_flags() {
+    let a = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    let b = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create another socket bound to 127.0.0.1:0");
+    let a_addr = a.local_addr().expect("get local addr of socket a");
+    let b_addr = b.local_addr().expect("get local addr of socket b");
+    b.set_nonblocking(true).expect("make recv fail instead of hanging");
+
+    let sent = a.send_to_with_flags(b"flagged", libc::MSG_DONTWAIT, b_addr)
+        .expect("send with MSG_DONTWAIT");
+    assert_eq!(sent, 7);
+    let mut buf = [0u8; 4];
+    let peeked = b.recv_with_flags(&mut buf, libc::MSG_PEEK | libc::MSG_TRUNC)
+        .expect("peek at datagram");
+    assert_eq!(peeked, (7, a_addr), "MSG_TRUNC returns the full length");
+    assert_eq!(&buf, b"flag");
+    let mut buf = [0u8; 10];
+    assert_eq!(b.recv_with_flags(&mut buf, 0).expect("receive peeked datagram"), (7, a_addr));
+    assert_eq!(&buf[..7], b"flagged");
+    b.recv_with_flags(&mut buf, 0).expect_err("the datagram was only received once");
+}
+
+#[test]
+fn connect_with_coverage() {
+    let a = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    let b = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create another socket bound to 127.0.0.1:0");
+    let a_addr = a.local_addr().expect("get local addr of socket a");
+    let b_addr = b.local_addr().expect("get local addr of socket b");
+    a.connect_with_coverage(b_addr, Some(4)).expect("connect and set coverage");
+    assert_eq!(a.peer_addr().expect("get peer addr"), b_addr);
+    assert_eq!(a.send_checksum_coverage().expect("get send cscov"), Some(4));
+    b.set_recv_checksum_coverage_filter(Some(4)).expect("set recv cscov filter");
+    b.set_nonblocking(true).expect("make recv fail instead of hanging");
+
+    let mut buf = [0u8; 20];
+    for _ in 0..2 {
+        assert_eq!(a.send(b"session datagram").expect("send connected"), 16);
+        assert_eq!(b.recv_from(&mut buf).expect("receive partially covered"), (16, a_addr));
+    }
+    assert_eq!(a.send_checksum_coverage().expect("get send cscov"), Some(4));
+}
+
+#[test]
+fn connect_symmetric_coverage() {
+    let a = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    let b = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create another socket bound to 127.0.0.1:0");
+    let a_addr = a.local_addr().expect("get local addr of socket a");
+    let b_addr = b.local_addr().expect("get local addr of socket b");
+    a.connect_symmetric_coverage(b_addr, 6).expect("connect and set coverage");
+    b.connect_symmetric_coverage(a_addr, 6).expect("connect and set coverage");
+    for socket in &[&a, &b] {
+        assert_eq!(socket.send_checksum_coverage().expect("get send cscov"), Some(6));
+        assert_eq!(socket.recv_checksum_coverage_filter().expect("get recv cscov filter"), Some(6));
+    }
+    assert_eq!(a.peer_addr().expect("get peer addr"), b_addr);
+
+    let mut buf = [0u8; 20];
+    b.send(b"ping").expect("send to a");
+    assert_eq!(a.recv(&mut buf).expect("receive from b"), 4);
+    a.send(b"pong").expect("send to b");
+    assert_eq!(b.recv(&mut buf).expect("receive from a"), 4);
+}
+
+#[test]
+fn connect_and_local_addr() {
+    let b = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    let b_addr = b.local_addr().expect("get local addr of socket b");
+    let a = UdpLiteSocket::new_unbound(AddressFamily::Ipv4).expect("create unbound socket");
+    let a_addr = a.connect_and_local_addr(b_addr).expect("connect and get local addr");
+    assert_eq!(a_addr.ip(), Ipv4Addr::LOCALHOST, "the IP of the route to the peer");
+    assert_ne!(a_addr.port(), 0, "connecting binds to an ephemeral port");
+    assert_eq!(a.local_addr().expect("get local addr"), a_addr);
+
+    b.set_nonblocking(true).expect("make recv fail instead of hanging");
+    a.send(b"from here").expect("send connected");
+    let mut buf = [0u8; 20];
+    assert_eq!(b.recv_from(&mut buf).expect("receive datagram"), (9, a_addr));
+}
+
+#[cfg(any(target_os="linux", target_os="android"))]
+#[test]
+fn drop_count_includes_coverage_filter() {
+    let a = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    let b = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create another socket bound to 127.0.0.1:0");
+    let b_addr = b.local_addr().expect("get local addr of socket b");
+    b.set_nonblocking(true).expect("make recv fail instead of hanging");
+    assert_eq!(b.drop_count().expect("get drop count"), 0);
+
+    b.set_recv_checksum_coverage_filter(Some(5)).expect("set recv cscov filter");
+    a.set_send_checksum_coverage(Some(2)).expect("set send cscov");
+    a.send_to(b"insufficiently covered", b_addr).expect("send datagram");
+    let mut buf = [0u8; 30];
+    b.recv(&mut buf).expect_err("datagram is discarded");
+    assert_eq!(b.drop_count().expect("get drop count"), 1);
+
+    a.set_send_checksum_coverage(Some(5)).expect("set send cscov");
+    a.send_to(b"sufficiently covered", b_addr).expect("send datagram");
+    assert_eq!(b.recv(&mut buf).expect("receive datagram"), 20);
+    assert_eq!(b.drop_count().expect("get drop count"), 1);
+}
+
+#[cfg(any(target_os="linux", target_os="android"))]
+#[test]
+fn recv_overflow_counter() {
+    use udplite::RecvOptions;
+    let a = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    let b = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create another socket bound to 127.0.0.1:0");
+    let b_addr = b.local_addr().expect("get local addr of socket b");
+    b.set_nonblocking(true).expect("make recv fail instead of hanging");
+    assert!(!b.recv_overflow_counter().expect("get overflow counter"), "disabled by default");
+    b.set_recv_overflow_counter(true).expect("enable overflow counter");
+    assert!(b.recv_overflow_counter().expect("get overflow counter"), "enabling works");
+    let mut buf = [0u8; 30];
+
+    a.send_to(b"before", b_addr).expect("send datagram");
+    let info = b.recv_msg(&mut buf, RecvOptions::new()).expect("receive datagram");
+    assert_eq!(info.dropped, None, "omitted while nothing is dropped");
+
+    // make b drop a datagram
+    b.set_recv_checksum_coverage_filter(Some(5)).expect("set recv cscov filter");
+    a.set_send_checksum_coverage(Some(2)).expect("set send cscov");
+    a.send_to(b"insufficiently covered", b_addr).expect("send datagram");
+    a.set_send_checksum_coverage(None).expect("cover entire datagrams");
+    a.send_to(b"after", b_addr).expect("send datagram");
+    let info = b.recv_msg(&mut buf, RecvOptions::new()).expect("receive datagram");
+    assert_eq!((info.len, info.dropped), (5, Some(1)));
+
+    b.set_recv_overflow_counter(false).expect("disable overflow counter");
+    a.send_to(b"disabled", b_addr).expect("send datagram");
+    let info = b.recv_msg(&mut buf, RecvOptions::new()).expect("receive datagram");
+    assert_eq!(info.dropped, None);
+}
+
+#[test]
+fn queue_lengths() {
+    let a = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    let b = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create another socket bound to 127.0.0.1:0");
+    let b_addr = b.local_addr().expect("get local addr of socket b");
+    assert_eq!(b.recv_queue_len().expect("get recv queue length"), 0);
+    a.send_queue_len().expect("get send queue length");
+
+    a.send_to(b"first", b_addr).expect("send first datagram");
+    a.send_to(b"second!", b_addr).expect("send second datagram");
+    let queued = b.recv_queue_len().expect("get recv queue length");
+    if cfg!(target_os="linux") {
+        assert_eq!(queued, 5, "Linux returns the length of the next datagram");
+    } else {
+        assert!(queued >= 12, "other OSes return the total");
+    }
+    let mut buf = [0u8; 10];
+    b.recv(&mut buf).expect("receive first datagram");
+    b.recv(&mut buf).expect("receive second datagram");
+    assert_eq!(b.recv_queue_len().expect("get recv queue length"), 0);
+}
+
+#[cfg(any(target_os="linux", target_os="android"))]
+#[test]
+fn flow_label() {
+    let a = UdpLiteSocket::bind((Ipv6Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to [::1]:0");
+    let b = UdpLiteSocket::bind((Ipv6Addr::LOCALHOST, 0))
+        .expect("create another socket bound to [::1]:0");
+    let b_addr = b.local_addr().expect("get local addr of socket b");
+    let label = 0x1234 + (a.local_addr().unwrap().port() as u32);
+    assert_eq!(a.set_flow_label(label).expect_err("not connected").kind(), ErrorKind::NotConnected);
+    a.connect(b_addr).expect("connect");
+    assert_eq!(a.flow_label().expect("get flow label"), 0);
+    a.set_flow_label(label).expect("set flow label");
+    assert_eq!(a.flow_label().expect("get flow label"), label);
+    a.set_flow_label(label).expect("set the same flow label again");
+    assert_eq!(a.peer_addr().expect("get peer addr").port(), b_addr.port());
+    a.send(b"labeled").expect("send with flow label");
+    let mut buf = [0u8; 10];
+    assert_eq!(b.recv(&mut buf).expect("receive labeled datagram"), 7);
+    let other_label = label + 1;
+    a.set_flow_label(other_label).expect("set a different flow label");
+    assert_eq!(a.flow_label().expect("get flow label"), other_label);
+    a.set_flow_label(label).expect("switch back to the first label, which is still leased");
+    assert_eq!(a.flow_label().expect("get flow label"), label);
+
+    let c = UdpLiteSocket::bind((Ipv6Addr::LOCALHOST, 0))
+        .expect("create a third socket bound to [::1]:0");
+    c.connect(b_addr).expect("connect");
+    let error = c.set_flow_label(label).expect_err("label is leased by socket a");
+    assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+    assert_eq!(c.flow_label().expect("get flow label"), 0, "unchanged");
+    assert_eq!(c.peer_addr().expect("still connected"), b_addr);
+
+    a.set_flow_label(0x10_0000).expect_err("flow label is too big");
+    a.set_flow_label(0).expect("clear flow label");
+    assert_eq!(a.flow_label().expect("get flow label"), 0);
+
+    let v4 = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    v4.connect(v4.local_addr().unwrap()).expect("connect IPv4 socket");
+    v4.set_flow_label(1).expect_err("flow labels are IPv6-only");
+}
+
+#[test]
+fn pair() {
+    let (a, b) = UdpLiteSocket::pair().expect("create connected pair");
+    assert_eq!(a.peer_addr().expect("get peer of a"), b.local_addr().expect("get addr of b"));
+    assert_eq!(b.peer_addr().expect("get peer of b"), a.local_addr().expect("get addr of a"));
+    assert!(a.local_addr().unwrap().ip().is_loopback());
+
+    a.set_send_checksum_coverage(Some(0)).expect("set send cscov");
+    assert_eq!(a.send(b"ping").expect("send from a"), 4);
+    let mut buf = [0u8; 10];
+    assert_eq!(b.recv(&mut buf).expect("receive on b"), 4);
+    assert_eq!(b.send(b"pong!").expect("send from b"), 5);
+    assert_eq!(a.recv(&mut buf).expect("receive on a"), 5);
+    assert_eq!(&buf[..5], b"pong!");
+}
+
+#[test]
+fn recv_from_array() {
+    let (a, b) = UdpLiteSocket::pair().expect("create connected pair");
+    let a_addr = a.local_addr().expect("get local addr of socket a");
+    a.send(b"fits").expect("send short datagram");
+    let (buf, len, from) = b.recv_from_array::<8>().expect("receive into array");
+    assert_eq!((len, from), (4, a_addr));
+    assert_eq!(&buf, b"fits\0\0\0\0");
+
+    a.send(b"truncated").expect("send long datagram");
+    let (buf, len, _) = b.recv_from_array::<5>().expect("receive truncated into array");
+    assert_eq!(len, 9, "returns the untruncated length");
+    assert_eq!(&buf, b"trunc");
+}
+
+// FreeBSD rejects coverage that only covers part of the header
+#[cfg(any(target_os="linux", target_os="android"))]
+#[test]
+fn partial_header_coverage_reads_as_header_only() {
+    use std::os::unix::io::AsRawFd;
+    let socket = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    // UDPLITE_SEND_CSCOV and UDPLITE_RECV_CSCOV
+    for &option in &[10, 11] {
+        let coverage: libc::c_int = 3;
+        let ret = unsafe {
+            libc::setsockopt(
+                socket.as_raw_fd(),
+                136,
+                option,
+                &coverage as *const libc::c_int as *const libc::c_void,
+                std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        assert_eq!(ret, 0, "set coverage to part of the header");
+    }
+    assert_eq!(socket.send_checksum_coverage().expect("get send cscov"), Some(0));
+    assert_eq!(socket.recv_checksum_coverage_filter().expect("get recv cscov"), Some(0));
+}
+
+#[test]
+fn recv_vec() {
+    let (a, b) = UdpLiteSocket::pair().expect("create connected pair");
+    let a_addr = a.local_addr().expect("get local addr of socket a");
+    a.send(b"owned").expect("send datagram");
+    let (datagram, from) = b.recv_vec(1000).expect("receive datagram into vec");
+    assert_eq!((&datagram[..], from), (&b"owned"[..], a_addr));
+    assert!(datagram.capacity() < 1000, "vec is shrunk");
+
+    a.send(b"too long").expect("send long datagram");
+    a.send(b"short").expect("send short datagram");
+    let error = b.recv_vec(5).expect_err("datagram is longer than max_len");
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    let (datagram, _) = b.recv_vec(5).expect("receive next datagram");
+    assert_eq!(&datagram[..], b"short");
+}
+
+#[test]
+fn dual_stack() {
+    let dual = UdpLiteSocket::bind_dual_stack(0).expect("create dual-stack socket");
+    assert_eq!(dual.domain().expect("get domain"), AddressFamily::Ipv6);
+    let port = dual.local_addr().expect("get local addr of dual-stack socket").port();
+    let v4 = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    let v4_addr = v4.local_addr().expect("get local addr of IPv4 socket");
+
+    v4.send_to(b"mapped", (Ipv4Addr::LOCALHOST, port)).expect("send to dual-stack socket");
+    let mut buf = [0u8; 10];
+    let (len, from) = dual.recv_from(&mut buf).expect("receive IPv4 datagram");
+    assert_eq!(&buf[..len], b"mapped");
+    assert_eq!(from.ip(), std::net::IpAddr::V6(Ipv4Addr::LOCALHOST.to_ipv6_mapped()));
+    assert_eq!(from.port(), v4_addr.port());
+
+    dual.send_to(b"reply", from).expect("reply to IPv4-mapped address");
+    assert_eq!(v4.recv(&mut buf).expect("receive reply"), 5);
+}
+
+#[test]
+fn recv_from_uninit() {
+    use std::mem::MaybeUninit;
+    let (a, b) = UdpLiteSocket::pair().expect("create connected pair");
+    let a_addr = a.local_addr().expect("get local addr of socket a");
+    a.send(b"uninit").expect("send datagram");
+    let mut buf = [MaybeUninit::<u8>::uninit(); 100];
+    let (len, from) = b.recv_from_uninit(&mut buf).expect("receive into uninitialized buffer");
+    assert_eq!((len, from), (6, a_addr));
+    let received = buf[..len].iter().map(|byte| unsafe { byte.assume_init() }).collect::<Vec<u8>>();
+    assert_eq!(&received[..], b"uninit");
+}
+
+#[test]
+fn recv_msg() {
+    use std::time::{Duration, SystemTime};
+    use udplite::RecvOptions;
+    for &ip in &[Ipv4Addr::LOCALHOST.into(), std::net::IpAddr::V6(Ipv6Addr::LOCALHOST)] {
+        let a = UdpLiteSocket::bind((ip, 0)).expect("create UDP-Lite socket");
+        let b = UdpLiteSocket::bind((ip, 0)).expect("create another UDP-Lite socket");
+        let a_addr = a.local_addr().expect("get local addr of socket a");
+        let b_addr = b.local_addr().expect("get local addr of socket b");
+        if ip.is_ipv4() {
+            a.set_ttl(42).expect("set TTL");
+        } else {
+            a.set_hop_limit(42).expect("set hop limit");
+        }
+        let mut buf = [0u8; 20];
+
+        a.send_to(b"plain", b_addr).expect("send datagram");
+        let info = b.recv_msg(&mut buf, RecvOptions::new()).expect("receive without metadata");
+        assert_eq!((info.len, info.src_addr), (5, a_addr));
+        assert_eq!((info.ttl, info.timestamp, info.dest, info.tos), (None, None, None, None));
+
+        let before = SystemTime::now() - Duration::from_secs(1);
+        a.send_to(b"metadata", b_addr).expect("send datagram");
+        let options = RecvOptions::new().ttl(true).timestamp(true).pktinfo(true).tos(true);
+        let info = b.recv_msg(&mut buf, options).expect("receive with metadata");
+        assert_eq!((info.len, info.src_addr), (8, a_addr));
+        assert_eq!(&buf[..8], b"metadata");
+        assert_eq!(info.ttl, Some(42));
+        assert_eq!(info.dest, Some(ip));
+        assert_eq!(info.tos, Some(0));
+        if !(cfg!(target_os="freebsd") && ip.is_ipv4()) {
+            assert!(info.ifindex.is_some(), "interface index is received");
+        }
+        let timestamp = info.timestamp.expect("timestamp is received");
+        assert!(timestamp > before && timestamp < before + Duration::from_secs(60));
+    }
+}
+
+#[cfg(any(target_os="linux", target_os="android"))]
+#[test]
+fn recv_timestamping() {
+    use std::time::{Duration, SystemTime};
+    use udplite::RecvOptions;
+    let (a, b) = UdpLiteSocket::pair().expect("create connected pair");
+    b.set_nonblocking(true).expect("make recv fail instead of hanging");
+    assert_eq!(b.timestamping().expect("get SO_TIMESTAMPING"), 0, "disabled by default");
+    let flags = libc::SOF_TIMESTAMPING_RX_SOFTWARE | libc::SOF_TIMESTAMPING_SOFTWARE
+        | libc::SOF_TIMESTAMPING_RX_HARDWARE | libc::SOF_TIMESTAMPING_RAW_HARDWARE;
+    b.set_timestamping(flags).expect("enable SO_TIMESTAMPING");
+    assert_eq!(b.timestamping().expect("get SO_TIMESTAMPING"), flags);
+
+    let before = SystemTime::now();
+    a.send(b"stamped").expect("send datagram");
+    let mut buf = [0u8; 10];
+    let info = b.recv_msg(&mut buf, RecvOptions::new()).expect("receive with timestamps");
+    let timestamp = info.timestamp.expect("software timestamp is received");
+    assert!(timestamp >= before - Duration::from_secs(1), "{:?} {:?}", timestamp, before);
+    assert_eq!(info.hardware_timestamp, None, "loopback doesn't support hardware timestamps");
+}
+
+#[cfg(any(target_os="linux", target_os="android"))]
+#[test]
+fn tx_timestamps_from_error_queue() {
+    let (a, b) = UdpLiteSocket::pair().expect("create connected pair");
+    let mut buf = [0u8; 100];
+    let error = a.recv_error(&mut buf).expect_err("error queue is empty");
+    assert_eq!(error.kind(), ErrorKind::WouldBlock, "reading the error queue never blocks");
+
+    let flags = libc::SOF_TIMESTAMPING_TX_SOFTWARE | libc::SOF_TIMESTAMPING_SOFTWARE
+        | libc::SOF_TIMESTAMPING_OPT_ID | libc::SOF_TIMESTAMPING_OPT_TSONLY;
+    a.set_timestamping(flags).expect("enable transmit timestamps");
+    a.send(b"first").expect("send first datagram");
+    a.send(b"second").expect("send second datagram");
+    for id in 0..2 {
+        let entry = a.recv_error(&mut buf).expect("receive transmit timestamp");
+        assert_eq!((entry.errno, entry.tx_id), (0, Some(id)));
+        assert_eq!(entry.tx_type, Some(0), "SCM_TSTAMP_SND");
+        assert!(entry.tx_timestamp.is_some(), "software timestamp is received");
+        assert_eq!(entry.tx_hardware_timestamp, None, "not enabled");
+        assert_eq!(entry.len, 0, "OPT_TSONLY");
+    }
+    b.recv(&mut buf).expect("the datagrams were sent normally");
+}
+
+#[cfg(any(target_os="linux", target_os="android"))]
+#[test]
+fn attach_filter() {
+    use udplite::SockFilter;
+    let (a, b) = UdpLiteSocket::pair().expect("create connected pair");
+    let mut buf = [0u8; 20];
+    let error = b.detach_filter().expect_err("no filter is attached");
+    assert_eq!(error.raw_os_error(), Some(libc::ENOENT));
+
+    // BPF_LD | BPF_B | BPF_ABS: load the first payload byte, after the 8-byte header
+    // BPF_JMP | BPF_JEQ | BPF_K: keep datagrams starting with 'k', drop the rest
+    let keep_k = [
+        SockFilter::new(0x30, 0, 0, 8),
+        SockFilter::new(0x15, 0, 1, b'k' as u32),
+        SockFilter::new(0x06, 0, 0, 0xffff),
+        SockFilter::new(0x06, 0, 0, 0),
+    ];
+    b.attach_filter(&keep_k).expect("attach filter");
+    a.send(b"drop").expect("send datagram that is filtered out");
+    a.send(b"keep").expect("send datagram that passes the filter");
+    assert_eq!(b.recv(&mut buf).expect("receive"), 4);
+    assert_eq!(&buf[..4], b"keep", "the first datagram was dropped");
+
+    b.detach_filter().expect("detach filter");
+    a.send(b"drop").expect("send datagram");
+    assert_eq!(b.recv(&mut buf).expect("receive"), 4);
+    assert_eq!(&buf[..4], b"drop", "no longer filtered");
+
+    let error = b.attach_filter(&[]).expect_err("empty programs are invalid");
+    assert_eq!(error.raw_os_error(), Some(libc::EINVAL));
+}
+
+#[cfg(any(target_os="linux", target_os="android"))]
+#[test]
+fn attach_bpf_rejects_non_programs() {
+    use std::os::unix::io::AsRawFd;
+    let socket = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    let error = socket.attach_bpf(-1).expect_err("not a file descriptor");
+    assert_eq!(error.raw_os_error(), Some(libc::EBADF));
+    let error = socket.attach_bpf(socket.as_raw_fd()).expect_err("not an eBPF program");
+    assert_eq!(error.raw_os_error(), Some(libc::EINVAL));
+}
+
+#[test]
+fn recv_orig_dst() {
+    use udplite::RecvOptions;
+    for &ip in &[Ipv4Addr::LOCALHOST.into(), std::net::IpAddr::V6(Ipv6Addr::LOCALHOST)] {
+        let ip: std::net::IpAddr = ip;
+        let a = UdpLiteSocket::bind((ip, 0)).expect("create UDP-Lite socket");
+        let unspecified = if ip.is_ipv4() {
+            std::net::IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+        } else {
+            std::net::IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+        };
+        let b = UdpLiteSocket::bind((unspecified, 0)).expect("create UDP-Lite socket bound to any");
+        let b_port = b.local_addr().expect("get local addr of socket b").port();
+        let mut buf = [0u8; 20];
+
+        a.send_to(b"plain", (ip, b_port)).expect("send datagram");
+        let info = b.recv_msg(&mut buf, RecvOptions::new()).expect("receive without metadata");
+        assert_eq!(info.orig_dst, None);
+
+        assert!(!b.recv_orig_dst().expect("get recv orig dst"), "disabled by default");
+        a.send_to(b"not redirected", (ip, b_port)).expect("send datagram");
+        let info = b.recv_msg(&mut buf, RecvOptions::new().orig_dst(true))
+            .expect("receive with original destination");
+        assert_eq!(info.len, 14);
+        let orig_dst = info.orig_dst.expect("original destination is received");
+        assert_eq!((orig_dst.ip(), orig_dst.port()), (ip, b_port));
+        assert!(b.recv_orig_dst().expect("get recv orig dst"), "recv_msg() enables it");
+        b.set_recv_orig_dst(false).expect("disable receiving orig dst");
+        assert!(!b.recv_orig_dst().expect("get recv orig dst"), "disabling works");
+    }
+}
+
+#[test]
+fn recv_from_raw() {
+    let (a, b) = UdpLiteSocket::pair().expect("create connected pair");
+    let a_addr = a.local_addr().expect("get local addr of socket a");
+    a.send(b"raw").expect("send datagram");
+    a.send(b"again").expect("send another datagram");
+    let mut storage = unsafe { std::mem::zeroed::<libc::sockaddr_storage>() };
+    let mut buf = [0u8; 10];
+    let (len, addr_len) = b.recv_from_raw(&mut buf, &mut storage).expect("receive datagram");
+    assert_eq!(&buf[..len], b"raw");
+    let first = udplite::sockaddr_to_rust_addr(&storage, addr_len).expect("convert address");
+    assert_eq!(first, a_addr);
+    let (len, again_len) = b.recv_from_raw(&mut buf, &mut storage).expect("reuse storage");
+    assert_eq!((&buf[..len], again_len), (&b"again"[..], addr_len));
+}
+
+#[test]
+fn peek_msg() {
+    use udplite::RecvOptions;
+    let (a, b) = UdpLiteSocket::pair().expect("create connected pair");
+    let a_addr = a.local_addr().expect("get local addr of socket a");
+    a.send(b"route:payload").expect("send datagram");
+    let mut header = [0u8; 6];
+    let info = b.peek_msg(&mut header, RecvOptions::new().ttl(true)).expect("peek at datagram");
+    assert_eq!((info.len, info.src_addr, &header), (6, a_addr, b"route:"));
+    assert!(info.ttl.is_some(), "metadata is received when peeking");
+    let info = b.peek_msg(&mut header, RecvOptions::new()).expect("peek again");
+    assert_eq!(info.len, 6);
+
+    let mut buf = [0u8; 20];
+    let info = b.recv_msg(&mut buf, RecvOptions::new()).expect("receive peeked datagram");
+    assert_eq!(&buf[..info.len], b"route:payload", "the datagram was not consumed");
+    b.set_nonblocking(true).expect("make recv fail instead of hanging");
+    b.recv(&mut buf).expect_err("the datagram was consumed by recv_msg()");
+}
+
+#[test]
+fn persistent_recv_metadata() {
+    use udplite::RecvOptions;
+    for &ip in &[Ipv4Addr::LOCALHOST.into(), std::net::IpAddr::V6(Ipv6Addr::LOCALHOST)] {
+        let a = UdpLiteSocket::bind((ip, 0)).expect("create UDP-Lite socket");
+        let b = UdpLiteSocket::bind((ip, 0)).expect("create another UDP-Lite socket");
+        let b_addr = b.local_addr().expect("get local addr of socket b");
+        assert!(!b.recv_pktinfo().expect("get recv pktinfo"), "disabled by default");
+        assert!(!b.recv_ttl().expect("get recv TTL"), "disabled by default");
+        assert!(!b.recv_timestamp().expect("get recv timestamp"), "disabled by default");
+        b.set_recv_pktinfo(true).expect("enable receiving pktinfo");
+        b.set_recv_ttl(true).expect("enable receiving TTL");
+        b.set_recv_timestamp(true).expect("enable receiving timestamp");
+        assert!(b.recv_pktinfo().expect("get recv pktinfo"), "enabling works");
+        assert!(b.recv_ttl().expect("get recv TTL"), "enabling works");
+        assert!(b.recv_timestamp().expect("get recv timestamp"), "enabling works");
+        let mut buf = [0u8; 20];
+
+        a.send_to(b"enabled", b_addr).expect("send datagram");
+        let info = b.recv_msg(&mut buf, RecvOptions::new()).expect("receive with metadata");
+        assert_eq!(info.dest, Some(ip));
+        assert!(info.ttl.is_some(), "TTL is received");
+        assert!(info.timestamp.is_some(), "timestamp is received");
+        assert_eq!(info.tos, None, "not enabled");
+
+        b.set_recv_pktinfo(false).expect("disable receiving pktinfo");
+        b.set_recv_ttl(false).expect("disable receiving TTL");
+        b.set_recv_timestamp(false).expect("disable receiving timestamp");
+        a.send_to(b"disabled", b_addr).expect("send datagram");
+        let info = b.recv_msg(&mut buf, RecvOptions::new()).expect("receive without metadata");
+        assert_eq!((info.ttl, info.timestamp, info.dest, info.tos), (None, None, None, None));
+    }
+}
+
+#[test]
+fn recv_many() {
+    let a = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    let b = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create another socket bound to 127.0.0.1:0");
+    let a_addr = a.local_addr().expect("get local addr of socket a");
+    let b_addr = b.local_addr().expect("get local addr of socket b");
+    b.set_nonblocking(true).expect("make recv fail instead of hanging");
+
+    a.send_all_to(&[(b_addr, b"one"), (b_addr, b"two!"), (b_addr, b"three")])
+        .expect("send three datagrams");
+    let mut bufs = [[0u8; 4]; 4];
+    let mut slices = bufs.iter_mut().map(|buf| &mut buf[..]).collect::<Vec<&mut [u8]>>();
+    let received = b.recv_many(&mut slices).expect("receive multiple datagrams");
+    assert_eq!(received, vec![(3, a_addr), (4, a_addr), (5, a_addr)], "full lengths");
+    assert_eq!(&bufs[..3], &[*b"one\0", *b"two!", *b"thre"]);
+    assert_eq!(b.recv_many(&mut []).expect("receive nothing"), Vec::new());
+    let mut buf = [0u8; 10];
+    let error = b.recv_many(&mut [&mut buf[..]]).expect_err("no more datagrams");
+    assert_eq!(error.kind(), ErrorKind::WouldBlock);
+}
+
+#[test]
+fn recv_many_vectored() {
+    use std::io::IoSliceMut;
+    let (a, b) = UdpLiteSocket::pair().expect("create connected pair");
+    let a_addr = a.local_addr().expect("get local addr of socket a");
+    a.send(b"HDRfirst").expect("send first datagram");
+    a.send(b"HDRsecond").expect("send second datagram");
+
+    let (mut header1, mut payload1) = ([0u8; 3], [0u8; 10]);
+    let (mut header2, mut payload2) = ([0u8; 3], [0u8; 10]);
+    let mut first = [IoSliceMut::new(&mut header1), IoSliceMut::new(&mut payload1)];
+    let mut second = [IoSliceMut::new(&mut header2), IoSliceMut::new(&mut payload2)];
+    let received = b.recv_many_vectored(&mut [&mut first, &mut second])
+        .expect("scatter-receive multiple datagrams");
+    assert_eq!(received, vec![(8, a_addr), (9, a_addr)]);
+    assert_eq!((&header1, &payload1[..5]), (b"HDR", &b"first"[..]));
+    assert_eq!((&header2, &payload2[..6]), (b"HDR", &b"second"[..]));
+
+    a.send(b"HDRtruncated").expect("send datagram longer than the buffers");
+    let (mut header, mut payload) = ([0u8; 3], [0u8; 4]);
+    let mut short = [IoSliceMut::new(&mut header), IoSliceMut::new(&mut payload)];
+    let received = b.recv_many_vectored(&mut [&mut short]).expect("receive truncated datagram");
+    assert_eq!(received, vec![(12, a_addr)], "full length");
+    assert_eq!((&header, &payload), (b"HDR", b"trun"));
+}
+
+#[test]
+fn recv_many_at_least() {
+    use std::time::Duration;
+    let (a, b) = UdpLiteSocket::pair().expect("create connected pair");
+    let a_addr = a.local_addr().expect("get local addr of socket a");
+    let sender = std::thread::spawn(move || {
+        for datagram in &[&b"one"[..], b"two", b"three"] {
+            std::thread::sleep(Duration::from_millis(20));
+            a.send(datagram).expect("send datagram");
+        }
+        a
+    });
+
+    let mut bufs = [[0u8; 5]; 4];
+    let mut bufs = bufs.iter_mut().map(|buf| &mut buf[..]).collect::<Vec<&mut[u8]>>();
+    let received = b.recv_many_at_least(&mut bufs, 2, Some(Duration::from_secs(5)))
+        .expect("receive at least two datagrams");
+    assert!(received.len() >= 2, "{:?}", received);
+    assert_eq!(&received[..2], &[(3, a_addr), (3, a_addr)]);
+    assert_eq!((&*bufs[0], &*bufs[1]), (&b"one\0\0"[..], &b"two\0\0"[..]));
+    let a = sender.join().expect("the sender thread doesn't panic");
+
+    a.send(b"four").expect("send datagram");
+    let received = b.recv_many_at_least(&mut bufs, 10, Some(Duration::from_millis(100)))
+        .expect("receive until timeout");
+    assert!(!received.is_empty(), "returns what was received before the timeout");
+    assert_eq!(received.last(), Some(&(4, a_addr)));
+    let received = b.recv_many_at_least(&mut bufs, 1, Some(Duration::from_millis(10)))
+        .expect("time out");
+    assert_eq!(received, Vec::new());
+}
+
+#[test]
+fn read_and_write() {
+    use std::io::{Read, Write};
+    let (a, mut b) = UdpLiteSocket::pair().expect("create connected pair");
+    assert_eq!((&a).write(b"first").expect("write datagram"), 5);
+    (&a).write_all(b"second").expect("write another datagram");
+    (&a).flush().expect("flush does nothing");
+    let mut buf = [0u8; 10];
+    assert_eq!(b.read(&mut buf).expect("read datagram"), 5);
+    assert_eq!(&buf[..5], b"first");
+    let mut short = [0u8; 3];
+    assert_eq!((&b).read(&mut short).expect("read truncated datagram"), 3);
+    assert_eq!(&short, b"sec");
+
+    let too_big = vec![0u8; 70_000];
+    let error = (&a).write(&too_big).expect_err("too big for one datagram");
+    assert_eq!(error.raw_os_error(), Some(libc::EMSGSIZE));
+}
+
+#[test]
+fn recv_batch() {
+    use udplite::BatchBuffer;
+    let (a, b) = UdpLiteSocket::pair().expect("create connected pair");
+    let a_addr = a.local_addr().expect("get local addr of socket a");
+    b.set_nonblocking(true).expect("make recv fail instead of hanging");
+    let mut batch = BatchBuffer::new(3, 4);
+    assert_eq!((batch.datagrams(), batch.max_len()), (3, 4));
+
+    for datagram in &[&b"one"[..], b"two!", b"three", b"four"] {
+        a.send(datagram).expect("send datagram");
+    }
+    let received = b.recv_batch(&mut batch).expect("receive batch")
+        .collect::<Vec<(&[u8], std::net::SocketAddr)>>();
+    assert_eq!(received, vec![(&b"one"[..], a_addr), (b"two!", a_addr), (b"thre", a_addr)]);
+    let received = b.recv_batch(&mut batch).expect("reuse buffer")
+        .map(|(datagram, _)| datagram.to_vec())
+        .collect::<Vec<Vec<u8>>>();
+    assert_eq!(received, vec![b"four".to_vec()]);
+    let error = b.recv_batch(&mut batch).err().expect("no more datagrams");
+    assert_eq!(error.kind(), ErrorKind::WouldBlock);
+
+    let mut empty = BatchBuffer::new(0, 100);
+    assert_eq!(b.recv_batch(&mut empty).expect("receive nothing").count(), 0);
+}
+
+#[test]
+fn disconnect() {
+    let (a, b) = UdpLiteSocket::pair().expect("create connected pair");
+    let c = UdpLiteSocket::bind((a.local_addr().unwrap().ip(), 0))
+        .expect("create a third socket");
+    let c_addr = c.local_addr().expect("get local addr of socket c");
+    a.set_nonblocking(true).expect("make recv fail instead of hanging");
+    c.send_to(b"filtered", a.local_addr().unwrap()).expect("send from unconnected peer");
+    let mut buf = [0u8; 10];
+    a.recv(&mut buf).expect_err("datagrams from others are filtered while connected");
+
+    let a_addr = a.local_addr().expect("get local addr of socket a");
+    a.disconnect().expect("disconnect");
+    assert!(!a.is_connected());
+    assert_eq!(a.local_addr().expect("get local addr after disconnecting"), a_addr);
+    a.send(b"nowhere").expect_err("no default destination after disconnecting");
+    c.send_to(b"accepted", a_addr).expect("send from other peer");
+    assert_eq!(a.recv_from(&mut buf).expect("receive from any peer"), (8, c_addr));
+    b.send(b"still").expect("send from previous peer");
+    assert_eq!(a.recv(&mut buf).expect("receive from previous peer"), 5);
+    a.disconnect().expect("disconnecting again is not an error");
+}
+
+#[test]
+fn disconnect_wildcard() {
+    let a = UdpLiteSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .expect("create UDP-Lite socket bound to 0.0.0.0:0");
+    let port = a.local_addr().expect("get local addr").port();
+    a.connect((Ipv4Addr::LOCALHOST, port)).expect("connect to itself");
+    a.disconnect().expect("disconnect");
+    let addr = a.local_addr().expect("get local addr after disconnecting");
+    assert_eq!(addr, (Ipv4Addr::UNSPECIFIED, port).into(), "binds to the wildcard address again");
+}
+
+#[test]
+fn recv_from_timeout() {
+    use std::time::{Duration, Instant};
+    let (a, b) = UdpLiteSocket::pair().expect("create connected pair");
+    let a_addr = a.local_addr().expect("get local addr of socket a");
+    let mut buf = [0u8; 10];
+    let started = Instant::now();
+    let result = b.recv_from_timeout(&mut buf, Duration::from_millis(50)).expect("wait");
+    assert_eq!(result, None, "times out");
+    assert!(started.elapsed() >= Duration::from_millis(50), "waits for the entire timeout");
+    assert_eq!(b.read_timeout().expect("get read timeout"), None, "the socket is unchanged");
+
+    a.send(b"in time").expect("send datagram");
+    let result = b.recv_from_timeout(&mut buf, Duration::from_secs(10)).expect("receive");
+    assert_eq!(result, Some((7, a_addr)));
+    a.send(b"now").expect("send another datagram");
+    let result = b.recv_from_timeout(&mut buf, Duration::from_secs(0)).expect("receive");
+    assert_eq!(result, Some((3, a_addr)), "zero timeout still receives available datagrams");
+    a.send(b"later").expect("send a third datagram");
+    let result = b.recv_from_timeout(&mut buf, Duration::MAX).expect("receive");
+    assert_eq!(result, Some((5, a_addr)), "huge timeouts don't overflow");
+}
+
+#[test]
+fn readable_and_writable_timeout() {
+    use std::time::Duration;
+    let (a, b) = UdpLiteSocket::pair().expect("create connected pair");
+    assert!(a.writable_timeout(None).expect("wait for writable"));
+    let readable = b.readable_timeout(Some(Duration::from_millis(20))).expect("poll");
+    assert!(!readable, "nothing to receive");
+    a.send(b"ready").expect("send datagram");
+    assert!(b.readable_timeout(None).expect("wait for readable"));
+    assert!(b.readable_timeout(Some(Duration::from_secs(0))).expect("poll again"),
+        "polling doesn't consume the datagram"
+    );
+}
+
+#[cfg(target_os="linux")]
+#[test]
+fn send_to_scoped() {
+    // find a link-local address, which the loopback interface doesn't have
+    let interfaces = std::fs::read_to_string("/proc/net/if_inet6").expect("list IPv6 addresses");
+    let link_local = interfaces.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<&str>>())
+        .find(|fields| fields[0].starts_with("fe80"));
+    let (addr, ifindex) = match link_local {
+        Some(fields) => {
+            let addr = u128::from_str_radix(fields[0], 16).expect("parse address");
+            (Ipv6Addr::from(addr), u32::from_str_radix(fields[1], 16).expect("parse index"))
+        }
+        None => return, // no interface with an IPv6 link-local address
+    };
+
+    let socket = UdpLiteSocket::bind((Ipv6Addr::UNSPECIFIED, 0))
+        .expect("create UDP-Lite socket bound to [::]:0");
+    let port = socket.local_addr().expect("get local addr").port();
+    socket.send_to_scoped(b"scoped", addr, port, ifindex).expect("send to link-local address");
+    let mut buf = [0u8; 10];
+    socket.set_read_timeout(Some(std::time::Duration::from_secs(5))).unwrap();
+    let (len, from) = socket.recv_from(&mut buf).expect("receive from link-local address");
+    assert_eq!(&buf[..len], b"scoped");
+    match from {
+        std::net::SocketAddr::V6(from) => {
+            assert_eq!(*from.ip(), addr);
+            assert_eq!(from.scope_id(), ifindex);
+        }
+        std::net::SocketAddr::V4(_) => panic!("received from IPv4 address"),
+    }
+}
+
+#[test]
+fn send_multicast_v6() {
+    use std::net::SocketAddrV6;
+    use std::time::Duration;
+    let receiver = UdpLiteSocket::bind((Ipv6Addr::UNSPECIFIED, 0))
+        .expect("create UDP-Lite socket bound to [::]:0");
+    let port = receiver.local_addr().expect("get local addr").port();
+    let group = SocketAddrV6::new(Ipv6Addr::new(0xff12, 0, 0, 0, 0, 0, 0x7564, 0x706c), port, 0, 0);
+    let sender = UdpLiteSocket::bind((Ipv6Addr::UNSPECIFIED, 0))
+        .expect("create another socket bound to [::]:0");
+
+    let unicast = SocketAddrV6::new(Ipv6Addr::LOCALHOST, port, 0, 0);
+    let error = sender.send_multicast_v6(b"group", unicast, 1).expect_err("not multicast");
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    sender.send_multicast_v6(b"group", group, 0x7fff_ffff)
+        .expect_err("the interface is used for the datagram");
+
+    // use the first interface that supports multicast, if any
+    let mut buf = [0u8; 10];
+    for ifindex in 1..16 {
+        if receiver.join_multicast_v6(group.ip(), ifindex).is_err() {
+            continue;
+        }
+        if sender.send_multicast_v6(b"group", group, ifindex).is_ok() {
+            let received = receiver.recv_from_timeout(&mut buf, Duration::from_secs(1))
+                .expect("receive multicast datagram");
+            assert_eq!(received.map(|(len, _)| len), Some(5), "looped back");
+            break;
+        }
+        receiver.leave_multicast_v6(group.ip(), ifindex).expect("leave group");
+    }
+}
+
+#[test]
+fn nonblocking_methods_would_block() {
+    use std::io::IoSliceMut;
+    use udplite::RecvOptions;
+    let socket = UdpLiteSocket::bind_nonblocking((Ipv4Addr::LOCALHOST, 0))
+        .expect("create nonblocking UDP-Lite socket bound to 127.0.0.1:0");
+    let mut buf = [0u8; 10];
+    fn kind<T>(result: Result<T, std::io::Error>) -> ErrorKind {
+        result.map(|_| ()).expect_err("would block").kind()
+    }
+    assert_eq!(kind(socket.recv_with_flags(&mut buf, 0)), ErrorKind::WouldBlock);
+    assert_eq!(kind(socket.recv_msg(&mut buf, RecvOptions::new())), ErrorKind::WouldBlock);
+    assert_eq!(kind(socket.recv_vec(10)), ErrorKind::WouldBlock);
+    assert_eq!(kind(socket.recv_many(&mut [&mut buf[..]])), ErrorKind::WouldBlock);
+    let mut vectored = [IoSliceMut::new(&mut buf)];
+    assert_eq!(kind(socket.recv_many_vectored(&mut [&mut vectored[..]])), ErrorKind::WouldBlock);
+    assert_eq!(socket.try_recv(&mut buf).expect("try receive"), None);
+}
+
+#[test]
+fn reply_to_mirrors_tos() {
+    use std::os::unix::io::AsRawFd;
+    use udplite::RecvOptions;
+    let families = [
+        (Ipv4Addr::LOCALHOST.into(), libc::IPPROTO_IP, libc::IP_TOS),
+        (Ipv6Addr::LOCALHOST.into(), libc::IPPROTO_IPV6, libc::IPV6_TCLASS),
+    ];
+    for &(ip, level, name) in &families {
+        let ip: std::net::IpAddr = ip;
+        let client = UdpLiteSocket::bind((ip, 0)).expect("create client socket");
+        let server = UdpLiteSocket::bind((ip, 0)).expect("create server socket");
+        let tos: libc::c_int = 0x28;
+        let ret = unsafe {
+            libc::setsockopt(
+                client.as_raw_fd(),
+                level,
+                name,
+                &tos as *const libc::c_int as *const libc::c_void,
+                std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        assert_eq!(ret, 0, "set TOS / traffic class of sent packets");
+        client.set_read_timeout(Some(std::time::Duration::from_secs(5))).unwrap();
+        server.set_read_timeout(Some(std::time::Duration::from_secs(5))).unwrap();
+        let mut buf = [0u8; 10];
+
+        client.send_to(b"request", server.local_addr().unwrap()).expect("send request");
+        let request = server.recv_msg(&mut buf, RecvOptions::new().tos(true))
+            .expect("receive request");
+        assert_eq!(request.tos, Some(0x28));
+        assert_eq!(server.reply_to(&request, b"reply").expect("send reply"), 5);
+        let reply = client.recv_msg(&mut buf, RecvOptions::new().tos(true))
+            .expect("receive reply");
+        assert_eq!((reply.len, &buf[..5]), (5, &b"reply"[..]));
+        assert_eq!(reply.tos, Some(0x28), "reply has the same TOS as the request");
+
+        server.send_to(b"plain", client.local_addr().unwrap()).expect("send without TOS");
+        let plain = client.recv_msg(&mut buf, RecvOptions::new().tos(true))
+            .expect("receive datagram");
+        assert_eq!(plain.tos, Some(0), "the TOS of the server socket is unchanged");
+    }
+}
+
+#[test]
+fn send_to_dontfrag() {
+    let a = UdpLiteSocket::bind((Ipv6Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to [::1]:0");
+    let b = UdpLiteSocket::bind((Ipv6Addr::LOCALHOST, 0))
+        .expect("create another socket bound to [::1]:0");
+    b.set_read_timeout(Some(std::time::Duration::from_secs(5))).unwrap();
+    let sent = a.send_to_dontfrag(b"probe", b.local_addr().unwrap())
+        .expect("send with don't-fragment");
+    assert_eq!(sent, 5);
+    let mut buf = [0u8; 10];
+    let (len, from) = b.recv_from(&mut buf).expect("receive probe");
+    assert_eq!((&buf[..len], from), (&b"probe"[..], a.local_addr().unwrap()));
+
+    let v4 = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    let error = v4.send_to_dontfrag(b"probe", v4.local_addr().unwrap())
+        .expect_err("no per-datagram don't-fragment for IPv4");
+    assert_eq!(error.kind(), ErrorKind::Unsupported);
+}
+
+#[test]
+fn send_to_wrong_family() {
+    let v4 = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    let v6 = UdpLiteSocket::bind((Ipv6Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to [::1]:0");
+    let v4_addr = v4.local_addr().unwrap();
+    let v6_addr = v6.local_addr().unwrap();
+
+    let error = v4.send_to(b"mismatch", v6_addr).expect_err("send to IPv6 from IPv4 socket");
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    assert_eq!(error.to_string(), "cannot send to IPv6 address on an IPv4 UDP-Lite socket");
+    let error = v4.send_to_with_flags(b"mismatch", 0, v6_addr).expect_err("send with flags");
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    let error = v4.send_many(&[(v6_addr, &b"mismatch"[..])]).expect_err("send many");
+    assert_eq!((error.index, error.error.kind()), (0, ErrorKind::InvalidInput));
+
+    // bound to ::1, so it can't send IPv4 even though IPV6_V6ONLY is not set
+    let error = v6.send_to(b"mismatch", v4_addr).expect_err("send to IPv4 from IPv6 socket");
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    assert_eq!(error.to_string(), "cannot send to IPv4 address on an IPv6 UDP-Lite socket");
+
+    let dual = UdpLiteSocket::bind_dual_stack(0).expect("create dual-stack socket");
+    dual.send_to(b"allowed", v4_addr).expect("dual-stack sockets can send to IPv4 addresses");
+}
+
+#[test]
+fn connect_checked() {
+    use std::time::Duration;
+    let closed = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    let closed_addr = closed.local_addr().unwrap();
+    drop(closed);
+
+    let a = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    let error = a.connect_checked(closed_addr, Duration::from_secs(5))
+        .expect_err("nothing listens on the port");
+    assert_eq!(error.kind(), ErrorKind::ConnectionRefused);
+    assert_eq!(a.peer_addr().expect("still connected"), closed_addr);
+    let error = a.connect_checked(closed_addr, Duration::MAX)
+        .expect_err("huge timeouts don't overflow");
+    assert_eq!(error.kind(), ErrorKind::ConnectionRefused);
+
+    let b = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create another socket bound to 127.0.0.1:0");
+    a.connect_checked(b.local_addr().unwrap(), Duration::from_millis(50))
+        .expect("peer exists");
+    b.set_nonblocking(true).expect("make recv fail instead of hanging");
+    let (len, from) = b.recv_from(&mut [0; 10]).expect("receive probe");
+    assert_eq!((len, from), (0, a.local_addr().unwrap()), "the probe is empty");
+}
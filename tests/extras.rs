@@ -1,6 +1,35 @@
 extern crate udplite;
+extern crate libc;
 
-use udplite::UdpLiteSocket;
+use std::net::UdpSocket;
+use udplite::{UdpLiteSocket, EitherSocket};
+
+#[test]
+fn is_supported() {
+    assert!(udplite::is_supported(), "UDP-Lite is supported by OSes that tests are run on");
+}
+
+#[test]
+fn either_prefers_lite() {
+    let socket = EitherSocket::bind_preferring_lite("127.0.0.1:0")
+        .expect("create UDP-Lite or UDP socket");
+    assert!(socket.is_lite(), "UDP-Lite is supported by OSes that tests are run on");
+    socket.set_send_checksum_coverage(Some(0)).expect("set partial coverage for UDP-Lite");
+    assert_eq!(socket.send_checksum_coverage().expect("get send cscov"), Some(0));
+}
+
+#[test]
+fn either_udp_coverage() {
+    let socket = UdpSocket::bind("127.0.0.1:0").expect("create UDP socket");
+    let socket = EitherSocket::Udp(socket);
+    assert!(!socket.is_lite());
+    socket.set_send_checksum_coverage(None).expect("UDP covers everything");
+    socket.set_send_checksum_coverage(Some(0)).expect_err("UDP cannot cover partially");
+    assert_eq!(socket.send_checksum_coverage().expect("get send cscov"), None);
+    socket.set_recv_checksum_coverage_filter(Some(10)).expect("UDP always passes the filter");
+    assert_eq!(socket.recv_checksum_coverage_filter().expect("get recv cscov"), None);
+    socket.send_to(b"UDP", socket.local_addr().unwrap()).expect("send through Deref");
+}
 
 // Only checks that the flag is set, doesn't actually test whether the fd is closed or not
 
@@ -41,3 +70,363 @@ fn cloned_has_cloexec() {
         "cloned sockets have close-on-exec set even if disabled on the original"
     );
 }
+
+#[test]
+fn get_set_nonblocking() {
+    let socket = UdpLiteSocket::bind("127.0.0.1:0").expect("Create IPv4 UDP-Lite socket");
+    assert!(!socket.nonblocking().expect("get nonblocking"), "bind() creates blocking sockets");
+    socket.set_nonblocking(true).expect("enable nonblocking");
+    assert!(socket.nonblocking().expect("get nonblocking"), "enabling nonblocking works");
+    socket.set_nonblocking(false).expect("disable nonblocking");
+    assert!(!socket.nonblocking().expect("get nonblocking"), "disabling nonblocking works");
+
+    let socket = UdpLiteSocket::bind_nonblocking("127.0.0.1:0")
+        .expect("Create nonblocking IPv4 UDP-Lite socket");
+    assert!(
+        socket.nonblocking().expect("get nonblocking"),
+        "bind_nonblocking() creates nonblocking sockets"
+    );
+}
+
+#[cfg(feature="socket2")]
+#[test]
+fn socket2_conversions() {
+    extern crate socket2;
+    use socket2::{Socket, Domain, Type, Protocol};
+    use std::convert::TryFrom;
+    use std::net::SocketAddr;
+
+    let socket = Socket::new(Domain::ipv4(), Type::dgram(), Some(Protocol::from(136)))
+        .expect("create UDP-Lite socket with socket2");
+    socket.bind(&"127.0.0.1:0".parse::<SocketAddr>().unwrap().into())
+        .expect("bind socket2 socket");
+    let socket = UdpLiteSocket::try_from(socket).expect("convert UDP-Lite socket2 socket");
+    socket.set_send_checksum_coverage(Some(0)).expect("set cscov of converted socket");
+    let socket = Socket::from(socket);
+    assert!(socket.local_addr().is_ok());
+
+    let udp = Socket::new(Domain::ipv4(), Type::dgram(), None).expect("create UDP socket");
+    UdpLiteSocket::try_from(udp).expect_err("UDP socket is not UDP-Lite");
+}
+
+#[test]
+fn get_set_dont_route() {
+    let socket = UdpLiteSocket::bind("127.0.0.1:0").expect("Create IPv4 UDP-Lite socket");
+    assert!(!socket.dont_route().expect("get dont route"), "disabled by default");
+    socket.set_dont_route(true).expect("enable dont route");
+    assert!(socket.dont_route().expect("get dont route"), "enabling works");
+    socket.send_to(b"direct", socket.local_addr().unwrap()).expect("send to local address");
+    socket.set_dont_route(false).expect("disable dont route");
+    assert!(!socket.dont_route().expect("get dont route"), "disabling works");
+}
+
+#[test]
+fn get_set_recv_lowat() {
+    let a = UdpLiteSocket::bind("127.0.0.1:0").expect("Create IPv4 UDP-Lite socket");
+    let b = UdpLiteSocket::bind("127.0.0.1:0").expect("Create another IPv4 UDP-Lite socket");
+    assert_eq!(b.recv_lowat().expect("get recv low-water mark"), 1);
+    b.set_recv_lowat(100).expect("set recv low-water mark");
+    assert_eq!(b.recv_lowat().expect("get recv low-water mark"), 100);
+    b.set_recv_lowat(usize::MAX).expect_err("too big");
+    // datagrams smaller than the low-water mark are still received
+    b.set_read_timeout(Some(std::time::Duration::from_secs(5))).unwrap();
+    a.send_to(b"small", b.local_addr().unwrap()).expect("send datagram");
+    assert_eq!(b.recv(&mut [0; 10]).expect("receive datagram"), 5);
+    if cfg!(target_os="linux") {
+        b.set_recv_lowat(0).expect("set zero recv low-water mark");
+        assert_eq!(b.recv_lowat().expect("get recv low-water mark"), 1, "zero is stored as 1");
+    }
+}
+
+#[cfg(any(target_os="linux", target_os="android"))]
+#[test]
+fn get_set_priority() {
+    let socket = UdpLiteSocket::bind("127.0.0.1:0").expect("Create IPv4 UDP-Lite socket");
+    assert_eq!(socket.priority().expect("get priority"), 0);
+    socket.set_priority(3).expect("set unprivileged priority");
+    assert_eq!(socket.priority().expect("get priority"), 3);
+}
+
+#[cfg(any(target_os="linux", target_os="android"))]
+#[test]
+fn get_set_busy_poll() {
+    let socket = UdpLiteSocket::bind("127.0.0.1:0").expect("Create IPv4 UDP-Lite socket");
+    socket.busy_poll().expect("get busy poll");
+    // increasing requires CAP_NET_ADMIN
+    socket.set_busy_poll(0).expect("disable busy polling");
+    assert_eq!(socket.busy_poll().expect("get busy poll"), 0);
+}
+
+#[test]
+fn get_set_ttl_and_hop_limit() {
+    let v4 = UdpLiteSocket::bind("127.0.0.1:0").expect("Create IPv4 UDP-Lite socket");
+    v4.ttl().expect("get default TTL");
+    v4.set_ttl(7).expect("set TTL");
+    assert_eq!(v4.ttl().expect("get TTL"), 7);
+    assert_eq!(std::ops::Deref::deref(&v4).ttl().expect("get TTL via std"), 7);
+    v4.set_hop_limit(7).expect_err("hop limit is IPv6-only");
+
+    let v6 = UdpLiteSocket::bind("[::1]:0").expect("Create IPv6 UDP-Lite socket");
+    v6.hop_limit().expect("get default hop limit");
+    v6.set_hop_limit(11).expect("set hop limit");
+    assert_eq!(v6.hop_limit().expect("get hop limit"), 11);
+}
+
+#[test]
+fn cloned_cloexec_has_cloexec() {
+    let socket = UdpLiteSocket::bind("127.0.0.1:0").expect("Create IPv4 UDP-Lite socket");
+    socket.set_cloexec(false).expect("disable close-on-exec");
+    let clone = socket.try_clone_cloexec().expect("clone socket with close-on-exec");
+    assert!(clone.is_cloexec().expect("get close-on-exec"), "clone has close-on-exec set");
+    assert!(!socket.is_cloexec().expect("get close-on-exec"), "the original is unchanged");
+    assert_eq!(clone.local_addr().unwrap(), socket.local_addr().unwrap());
+    clone.set_send_checksum_coverage(Some(4)).expect("set send cscov on clone");
+    assert_eq!(socket.send_checksum_coverage().expect("get send cscov"), Some(4));
+}
+
+#[test]
+fn from_activation_fd() {
+    use std::os::unix::io::{AsRawFd, IntoRawFd};
+    let socket = UdpLiteSocket::bind("127.0.0.1:0").expect("Create IPv4 UDP-Lite socket");
+    let addr = socket.local_addr().unwrap();
+    socket.set_cloexec(false).expect("disable close-on-exec like systemd");
+    let socket = unsafe { UdpLiteSocket::from_activation_fd(socket.into_raw_fd()) }
+        .expect("adopt bound UDP-Lite socket");
+    assert_eq!(socket.local_addr().unwrap(), addr);
+    assert!(socket.is_cloexec().expect("get close-on-exec"), "close-on-exec is set");
+
+    let udp = UdpSocket::bind("127.0.0.1:0").expect("create UDP socket");
+    let error = unsafe { UdpLiteSocket::from_activation_fd(udp.as_raw_fd()) }
+        .expect_err("UDP socket is not UDP-Lite");
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    udp.local_addr().expect("the fd is not closed on failure");
+
+    let unbound = UdpLiteSocket::new_unbound(udplite::AddressFamily::Ipv4)
+        .expect("create unbound socket");
+    let error = unsafe { UdpLiteSocket::from_activation_fd(unbound.as_raw_fd()) }
+        .expect_err("socket is not bound");
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    unbound.socket_type().expect("the fd is not closed on failure");
+}
+
+#[test]
+fn adopt_fd() {
+    use std::os::unix::io::{FromRawFd, IntoRawFd};
+    let socket = UdpLiteSocket::new_unbound(udplite::AddressFamily::Ipv6)
+        .expect("create unbound socket");
+    let fd = unsafe { UdpSocket::from_raw_fd(socket.into_raw_fd()) };
+    let socket = UdpLiteSocket::adopt_fd(fd, true).expect("adopt unbound UDP-Lite socket");
+    assert!(socket.nonblocking().expect("get nonblocking"), "made non-blocking");
+    assert_eq!(socket.domain().expect("get domain"), udplite::AddressFamily::Ipv6);
+    let fd = unsafe { UdpSocket::from_raw_fd(socket.into_raw_fd()) };
+    let socket = UdpLiteSocket::adopt_fd(fd, false).expect("adopt non-blocking socket");
+    assert!(!socket.nonblocking().expect("get nonblocking"), "made blocking");
+
+    let udp = UdpSocket::bind("127.0.0.1:0").expect("create UDP socket");
+    let error = UdpLiteSocket::adopt_fd(udp, false).expect_err("UDP socket is not UDP-Lite");
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn protocol_and_type() {
+    use std::os::unix::io::{FromRawFd, IntoRawFd};
+    let socket = UdpLiteSocket::bind("127.0.0.1:0").expect("Create IPv4 UDP-Lite socket");
+    assert_eq!(socket.protocol().expect("get protocol"), 136);
+    assert_eq!(socket.socket_type().expect("get socket type"), libc::SOCK_DGRAM);
+
+    let udp = UdpSocket::bind("127.0.0.1:0").expect("create UDP socket");
+    let udp = unsafe { UdpLiteSocket::from_raw_fd(udp.into_raw_fd()) };
+    assert_ne!(udp.protocol().ok(), Some(136), "UDP socket is not UDP-Lite");
+    assert_eq!(udp.socket_type().expect("get socket type of UDP socket"), libc::SOCK_DGRAM);
+}
+
+#[test]
+fn raw_sockopt() {
+    let socket = UdpLiteSocket::bind("127.0.0.1:0").expect("Create IPv4 UDP-Lite socket");
+    let mut buf = [0u8; 8];
+    let len = socket.get_sockopt_raw(libc::SOL_SOCKET, libc::SO_TYPE, &mut buf)
+        .expect("get SO_TYPE");
+    assert_eq!(len, std::mem::size_of::<libc::c_int>());
+    assert_eq!(buf[..4], libc::SOCK_DGRAM.to_ne_bytes());
+
+    socket.set_sockopt_raw(libc::IPPROTO_IP, libc::IP_TTL, &9i32.to_ne_bytes()).expect("set TTL");
+    assert_eq!(socket.ttl().expect("get TTL"), 9);
+    socket.set_sockopt_raw(libc::IPPROTO_IP, libc::IP_TTL, &[]).expect_err("too short");
+    socket.get_sockopt_raw(libc::SOL_SOCKET, -1, &mut buf).expect_err("invalid option");
+}
+
+#[test]
+fn set_linger_fails() {
+    use std::time::Duration;
+    let socket = UdpLiteSocket::bind("127.0.0.1:0").expect("Create IPv4 UDP-Lite socket");
+    let error = socket.set_linger(Some(Duration::from_secs(1))).expect_err("linger is meaningless");
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    socket.set_linger(None).expect_err("disabling linger is meaningless too");
+}
+
+#[test]
+fn join_multicast_by_ifname() {
+    use std::io::ErrorKind;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    let loopback = if cfg!(target_os="freebsd") {"lo0"} else {"lo"};
+    let v4 = UdpLiteSocket::bind("0.0.0.0:0").expect("Create IPv4 UDP-Lite socket");
+    v4.join_multicast_by_ifname(IpAddr::V4(Ipv4Addr::new(239, 1, 2, 3)), loopback)
+        .expect("join IPv4 multicast group on loopback");
+    let error = v4.join_multicast_by_ifname(IpAddr::V4(Ipv4Addr::new(239, 1, 2, 4)), "no such if")
+        .expect_err("join on nonexistent interface");
+    assert_eq!(error.kind(), ErrorKind::NotFound);
+
+    let v6 = UdpLiteSocket::bind("[::]:0").expect("Create IPv6 UDP-Lite socket");
+    let group = Ipv6Addr::new(0xff12, 0, 0, 0, 0, 0, 0, 0x1234);
+    v6.join_multicast_by_ifname(IpAddr::V6(group), loopback)
+        .expect("join IPv6 multicast group on loopback");
+    let error = v6.join_multicast_by_ifname(IpAddr::V6(group), "no such if")
+        .expect_err("join on nonexistent interface");
+    assert_eq!(error.kind(), ErrorKind::NotFound);
+}
+
+#[cfg(any(target_os="linux", target_os="android"))]
+#[test]
+fn freebind_and_transparent() {
+    use std::io::ErrorKind;
+    use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+    use udplite::AddressFamily;
+    // TEST-NET-1 and the documentation prefix are not assigned to any interface
+    let non_local_v4 = SocketAddr::from((Ipv4Addr::new(192, 0, 2, 1), 0));
+    let non_local_v6 = SocketAddr::from((Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1), 0));
+
+    let socket = UdpLiteSocket::new_unbound(AddressFamily::Ipv4).expect("create unbound socket");
+    assert!(!socket.is_bound());
+    assert!(!socket.freebind().expect("get freebind"), "disabled by default");
+    let error = socket.bind_to(non_local_v4).expect_err("bind to non-local address");
+    assert_eq!(error.kind(), ErrorKind::AddrNotAvailable);
+    socket.set_freebind(true).expect("enable freebind");
+    assert!(socket.freebind().expect("get freebind"), "enabling works");
+    socket.bind_to(non_local_v4).expect("bind to non-local address with freebind");
+    assert_eq!(socket.local_addr().unwrap().ip(), non_local_v4.ip());
+    socket.bind_to(non_local_v4).expect_err("already bound");
+
+    let socket = UdpLiteSocket::new_unbound(AddressFamily::Ipv6).expect("create unbound socket");
+    socket.set_freebind(true).expect("enable freebind for IPv6");
+    socket.bind_to(non_local_v6).expect("bind to non-local IPv6 address with freebind");
+
+    let socket = UdpLiteSocket::new_unbound(AddressFamily::Ipv4).expect("create unbound socket");
+    assert!(!socket.transparent().expect("get transparent"), "disabled by default");
+    match socket.set_transparent(true) {
+        Ok(()) => assert!(socket.transparent().expect("get transparent"), "enabling works"),
+        Err(e) => assert_eq!(e.kind(), ErrorKind::PermissionDenied, "requires privileges"),
+    }
+}
+
+#[cfg(target_os="freebsd")]
+#[test]
+fn reuse_port_lb() {
+    use std::net::Ipv4Addr;
+    use std::time::Duration;
+    use udplite::AddressFamily;
+    let first = UdpLiteSocket::new_unbound(AddressFamily::Ipv4).expect("create unbound socket");
+    assert!(!first.reuse_port_lb().expect("get SO_REUSEPORT_LB"), "disabled by default");
+    first.set_reuse_port_lb(true).expect("enable SO_REUSEPORT_LB");
+    assert!(first.reuse_port_lb().expect("get SO_REUSEPORT_LB"), "enabling works");
+    first.bind_to((Ipv4Addr::LOCALHOST, 0).into()).expect("bind first socket");
+    let addr = first.local_addr().unwrap();
+    let second = UdpLiteSocket::new_unbound(AddressFamily::Ipv4).expect("create unbound socket");
+    second.set_reuse_port_lb(true).expect("enable SO_REUSEPORT_LB");
+    second.bind_to(addr).expect("bind second socket to the same port");
+
+    // datagrams from one port go to the same socket, so send from many
+    for _ in 0..32 {
+        let sender = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0)).expect("create sender");
+        sender.send_to(b"balanced", addr).expect("send datagram");
+    }
+    let mut buf = [0u8; 10];
+    let mut received = [0; 2];
+    for (socket, count) in [&first, &second].iter().zip(&mut received) {
+        let timeout = Duration::from_millis(100);
+        while socket.recv_from_timeout(&mut buf, timeout).expect("receive datagram").is_some() {
+            *count += 1;
+        }
+    }
+    assert_eq!(received[0] + received[1], 32);
+    assert!(received[0] > 0 && received[1] > 0, "both sockets receive datagrams");
+}
+
+#[cfg(any(target_os="linux", target_os="android"))]
+#[test]
+fn kernel_info() {
+    let before = udplite::kernel_info().expect("read kernel info");
+    assert!(before.ipv4 || before.ipv6, "UDP-Lite is supported");
+    let in_errors = |info: &udplite::UdpLiteKernelInfo| {
+        info.counters.iter()
+            .find(|(name, _)| name == "InErrors")
+            .map(|&(_, count)| count)
+            .expect("has InErrors counter")
+    };
+    assert!(before.counters.iter().any(|(name, _)| name == "InDatagrams"));
+    if before.ipv6 {
+        assert!(before.counters6.iter().any(|(name, _)| name == "InDatagrams"));
+    }
+    assert!(before.rmem_min.is_some());
+
+    let a = UdpLiteSocket::bind("127.0.0.1:0").expect("create socket");
+    let b = UdpLiteSocket::bind("127.0.0.1:0").expect("create another socket");
+    a.set_send_checksum_coverage(Some(0)).expect("set send cscov");
+    b.set_recv_checksum_coverage_filter(Some(10)).expect("set recv cscov filter");
+    a.send_to(b"insufficiently covered", b.local_addr().unwrap()).expect("send datagram");
+    b.set_nonblocking(true).unwrap();
+    let mut buf = [0u8; 30];
+    b.recv(&mut buf).expect_err("datagram is discarded");
+    let after = udplite::kernel_info().expect("read kernel info again");
+    assert!(in_errors(&after) > in_errors(&before), "discarded datagrams count as InErrors");
+}
+
+#[cfg(feature="serde")]
+#[test]
+fn from_config() {
+    use udplite::UdpLiteConfig;
+    fn assert_serde<T: serde::Serialize + serde::de::DeserializeOwned>() {}
+    assert_serde::<UdpLiteConfig>();
+
+    let config = UdpLiteConfig {
+        bind_addr: "127.0.0.1:0".parse().unwrap(),
+        send_coverage: Some(4),
+        recv_coverage_filter: Some(2),
+        recv_buffer_size: Some(65536),
+        reuse_port: true,
+        nonblocking: true,
+        ..UdpLiteConfig::default()
+    };
+    let socket = UdpLiteSocket::from_config(&config).expect("create configured socket");
+    assert_eq!(socket.send_checksum_coverage().expect("get send cscov"), Some(4));
+    assert_eq!(socket.recv_checksum_coverage_filter().expect("get recv cscov"), Some(2));
+    assert!(socket.nonblocking().expect("get nonblocking"));
+    let shared = UdpLiteConfig { bind_addr: socket.local_addr().unwrap(), ..config.clone() };
+    UdpLiteSocket::from_config(&shared).expect("bind to the same port with SO_REUSEPORT");
+
+    let defaults = UdpLiteSocket::from_config(&UdpLiteConfig::default())
+        .expect("create socket with default config");
+    assert_eq!(defaults.send_checksum_coverage().expect("get send cscov"), None);
+    assert!(!defaults.nonblocking().expect("get nonblocking"));
+    let plain = UdpLiteSocket::bind("0.0.0.0:0").expect("create socket with bind()");
+    assert_eq!(
+        defaults.recv_checksum_coverage_filter().expect("get recv cscov of default config"),
+        plain.recv_checksum_coverage_filter().expect("get recv cscov of bind()"),
+        "the filter is left at the OS default"
+    );
+    let sender = UdpLiteSocket::bind("127.0.0.1:0").expect("create sending socket");
+    sender.set_send_checksum_coverage(Some(0)).expect("set partial coverage");
+    let port = defaults.local_addr().unwrap().port();
+    sender.send_to(b"partial", ("127.0.0.1", port)).expect("send partially covered datagram");
+    let mut buf = [0u8; 10];
+    let received = defaults.recv_from_timeout(&mut buf, std::time::Duration::from_secs(1))
+        .expect("wait for partially covered datagram");
+    assert_eq!(received.map(|(len, _)| len), Some(7), "isn't filtered out");
+    let too_big = UdpLiteConfig {
+        // binding to it would fail with AddrInUse
+        bind_addr: socket.local_addr().unwrap(),
+        send_buffer_size: Some(usize::MAX),
+        ..UdpLiteConfig::default()
+    };
+    let error = UdpLiteSocket::from_config(&too_big).expect_err("buffer size is too big");
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput, "checked before binding");
+}
@@ -0,0 +1,26 @@
+//! In a separate file so that no other tests open or close file descriptors
+//! while this one counts them.
+
+#![cfg(any(target_os="linux", target_os="android"))]
+
+extern crate udplite;
+
+use std::net::Ipv4Addr;
+use udplite::UdpLiteSocket;
+
+fn open_fds() -> usize {
+    std::fs::read_dir("/proc/self/fd").expect("list open file descriptors").count()
+}
+
+#[test]
+fn failed_bind_closes_socket() {
+    let bound = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .expect("create UDP-Lite socket bound to 127.0.0.1:0");
+    let addr = bound.local_addr().expect("get local addr");
+    let baseline = open_fds();
+    for _ in 0..10 {
+        let error = UdpLiteSocket::bind(addr).expect_err("address is in use");
+        assert_eq!(error.kind(), std::io::ErrorKind::AddrInUse);
+    }
+    assert_eq!(open_fds(), baseline, "the sockets that failed to bind were closed");
+}
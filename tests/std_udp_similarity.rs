@@ -1,7 +1,7 @@
 extern crate udplite;
 
 use std::net::{UdpSocket, SocketAddr, ToSocketAddrs, Ipv4Addr, Ipv6Addr};
-use std::io;
+use std::{io, mem};
 use std::iter;
 use std::os::unix::io::{FromRawFd, IntoRawFd};
 use udplite::UdpLiteSocket;
@@ -17,6 +17,9 @@ fn debug_implementation_unbound() {
     let udplite_alt_dbg = format!("{:#?}", udplite_socket);
     let std_udp_alt_dbg = format!("{:#?}", std_udp_socket);
     assert_eq!(&udplite_alt_dbg[7..], &std_udp_alt_dbg[3..]);
+    // closing an invalid fd aborts
+    mem::forget(udplite_socket);
+    mem::forget(std_udp_socket);
 }
 
 #[test]
@@ -60,7 +63,10 @@ fn zero_addrs_error() {
     }
     let udplite_err = UdpLiteSocket::bind(NoAddrs).expect_err("no addrs provided");
     let std_udp_err = UdpSocket::bind(NoAddrs).expect_err("no addrs provided");
-    assert_eq!(format!("{:?}", udplite_err), format!("{:?}", std_udp_err));
+    // std uses a static message which cannot be created outside of it,
+    // so the Debug output differs
+    assert_eq!(udplite_err.kind(), std_udp_err.kind());
+    assert_eq!(udplite_err.to_string(), std_udp_err.to_string());
 }
 
 #[test]
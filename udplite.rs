@@ -6,88 +6,85 @@
  * copied, modified, or distributed except according to those terms.
  */
 
-//! Exposes the UDP-Lite socket type with an API similar to `std::net::UdpSocket`.
-//!
-//! UDP-Lite is a layer 3 networking protocol very similar to UDP, that allows
-//! receiving partially corrupted packets.
-//! 
-//! In addition to not being reliable (ie. datagrams can disappear), 
-//! UDP-Lite is only useful if the layer 2 protocol supports disabling checksums,
-//! and is not all that usable on the wider internet.
-//! (My ISPs router doesn't recognize the protocol, so its NAT drops all packets.)
-//! The protocol is also only implemented on Linux and FreeBSD.
-//! (It looks like Android hasn't disabled it, but I'm not certain).
-//!
-//! This crate is tested on both Linux (except Android) and FreeBSD.
-//!
-//! # Examples
-//!
-//! ```
-//! use udplite::UdpLiteSocket;
-//! use std::net::*;
-//!
-//! let a = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
-//!     .expect("create UDP-Lite socket bound to 127.0.0.1:0");
-//! let b = UdpLiteSocket::bind((Ipv4Addr::LOCALHOST, 0))
-//!     .expect("create another socket bound to 127.0.0.1:0");
-//!
-//! // reduce sent and required checksum coverage (whole datagram by default)
-//! a.set_send_checksum_coverage(Some(5)).expect("set partial checksum coverage");
-//! b.set_recv_checksum_coverage_filter(Some(5)).expect("set required checksum coverage");
-//!
-//! let b_addr = b.local_addr().expect("get addr of socket b");
-//! a.send_to(b"Hello UDP-Lite", b_addr).expect("send datagram");
-//!
-//! # b.set_nonblocking(true).expect("enable non-blocking for receiving just in case");
-//! let mut buf = [0u8; 20];
-//! let received_bytes = b.recv(&mut buf).expect("receive datagram");
-//! assert_eq!(received_bytes, "Hello UDP-Lite".len());
-//! assert_eq!(&buf[..5], b"Hello");
-//! ```
-//!
-//! # Current implementation details
-//!
-//! To significantly reduce the amount of `unsafe` code necessary in this crate,
-//! most methods are provided through `Deref` to [`UdpSocket`](https://doc.rust-lang.org/std/net/struct.UdpSocket.html).
-//! This creates one wart/gotcha/unsoundness though:
-//! `UdpSocket`s `.try_clone()` is available, returning an `UdpSocket` that is
-//! actually UDP-Lite. The method is shadowed by [`UdpLiteSocket`](struct.UdpLiteSocket.html)s
-//! own [`.try_clone()`]()(struct.UdpLiteSocket.html#method.try_clone)
-//!
-//! # Minimum Rust version
-//!
-//! udplite will require Rust 1.36.0 (for `std::io::IoSlice`).
-//!
-//! # Possible future features (open an issue if you want one)
-//!
-//! * Optional tokio integration.
-//! * Vectored I/O (`std`s `UdpSocket` doesn't have this yet either).
-//! * Exposing more POSIX socket options and flags for `send()` and `recv()`.
-//! * Sending and receiving multiple datagrams at a time.
-//! * Getting TTL and/or timestamp of received datagrams.
-
-#![cfg(any(target_os="linux", target_os="freebsd", target_os="android"))]
+//! The implementation for operating systems that support UDP-Lite.
 
 extern crate libc;
 #[cfg(feature="mio_06")]
 extern crate mio_06;
 #[cfg(feature="mio_07")]
 extern crate mio_07;
+#[cfg(feature="socket2")]
+extern crate socket2;
 
-use std::os::raw::{c_int, c_void};
+use std::os::raw::{c_int, c_short, c_void};
 use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
-use std::net::{UdpSocket, SocketAddr, ToSocketAddrs};
-use std::{fmt, io, mem};
+use std::net::{UdpSocket, SocketAddr, SocketAddrV4, SocketAddrV6, IpAddr, Ipv4Addr, Ipv6Addr};
+use std::net::ToSocketAddrs;
+use std::{fmt, io, mem, ptr};
+use std::io::{IoSliceMut, Read, Write};
+use std::mem::MaybeUninit;
+use std::ffi::{CStr, CString};
 use std::io::ErrorKind::*;
 use std::ops::Deref;
-use std::fmt::Debug;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+#[cfg(any(target_os="linux", target_os="android"))]
+use std::time::SystemTime;
+use std::fmt::{Debug, Display};
+use crate::{AddressFamily, Incoming, RecvOptions, RecvInfo, BatchSendError, FilterWarning};
+use crate::CoverageGuard;
+#[cfg(feature="serde")]
+use crate::UdpLiteConfig;
+#[cfg(feature="socket2")]
+use std::convert::TryFrom;
 
-use libc::{AF_INET, AF_INET6, SOCK_DGRAM, SOCK_CLOEXEC, SOCK_NONBLOCK};
+use libc::{AF_INET, AF_INET6, AF_UNSPEC, SOCK_DGRAM, SOCK_CLOEXEC, SOCK_NONBLOCK, SOL_SOCKET};
+#[cfg(feature="raw_coverage")]
+use libc::SOCK_RAW;
+use libc::{SO_DONTROUTE, SO_RCVLOWAT};
+#[cfg(feature="serde")]
+use libc::{SO_REUSEADDR, SO_REUSEPORT, SO_RCVBUF, SO_SNDBUF};
+use libc::{SO_TYPE, SO_PROTOCOL, ENOPROTOOPT, EPROTONOSUPPORT, EAFNOSUPPORT};
+use libc::{EINVAL, ENETUNREACH};
+#[cfg(any(target_os="linux", target_os="android"))]
+use libc::{SO_PRIORITY, SO_BUSY_POLL, EEXIST};
 #[cfg(not(target_os="android"))]
 use libc::IPPROTO_UDPLITE;
-use libc::{socket, bind, getsockopt, setsockopt, socklen_t};
+use libc::{socket, bind, connect, close, getsockopt, setsockopt, getsockname, socklen_t};
+use libc::{recv, recvfrom, MSG_DONTWAIT, MSG_TRUNC, MSG_PEEK};
+use libc::{sendto, sendmmsg, recvmmsg, mmsghdr, iovec};
+#[cfg(not(target_os="freebsd"))]
+use libc::MSG_WAITFORONE;
+use libc::{recvmsg, msghdr, cmsghdr, CMSG_FIRSTHDR, CMSG_NXTHDR, CMSG_DATA, CMSG_LEN};
+use libc::{sendmsg, CMSG_SPACE};
+use libc::{IPPROTO_IP, IPPROTO_IPV6, IP_RECVTOS, IPV6_RECVTCLASS, IPV6_TCLASS};
+use libc::{IP_TTL, IPV6_UNICAST_HOPS, IPV6_V6ONLY};
+#[cfg(not(target_os="freebsd"))]
+use libc::{IP_RECVTTL, IPV6_RECVHOPLIMIT};
+use libc::{IPV6_HOPLIMIT, IPV6_RECVPKTINFO, IPV6_PKTINFO, in6_pktinfo};
+use libc::{IP_RECVORIGDSTADDR, IP_ORIGDSTADDR, IPV6_RECVORIGDSTADDR, IPV6_ORIGDSTADDR};
+#[cfg(not(target_os="freebsd"))]
+use libc::{IP_PKTINFO, in_pktinfo};
+#[cfg(target_os="freebsd")]
+use libc::{IP_RECVDSTADDR, in_addr};
+#[cfg(target_os="freebsd")]
+use libc::SO_REUSEPORT_LB;
+use libc::{SO_TIMESTAMP, SCM_TIMESTAMP, timeval};
+#[cfg(any(target_os="linux", target_os="android"))]
+use libc::timespec;
+use libc::{if_nametoindex, getifaddrs, freeifaddrs, ifaddrs};
+use libc::{poll, pollfd, POLLIN, POLLOUT};
+use libc::IP_TOS;
+#[cfg(not(target_os="freebsd"))]
+use libc::IPV6_DONTFRAG;
 use libc::{sockaddr_storage, sockaddr_in, sockaddr_in6, sockaddr, sa_family_t};
-use libc::{ioctl, FIOCLEX, FIONCLEX, fcntl, F_GETFD, FD_CLOEXEC};
+use libc::{ioctl, FIONREAD, fcntl, F_GETFD, F_SETFD, F_DUPFD_CLOEXEC, FD_CLOEXEC, F_GETFL, O_NONBLOCK};
+
+#[cfg(any(target_os="linux", target_os="android"))]
+use libc::TIOCOUTQ;
+#[cfg(any(target_os="linux", target_os="android"))]
+use libc::{IP_FREEBIND, IP_TRANSPARENT};
+#[cfg(any(target_os="linux", target_os="android"))]
+use libc::{IP_RECVERR, IPV6_RECVERR, MSG_ERRQUEUE};
 
 #[cfg(feature="mio_06")]
 use mio_06::{event::Evented, unix::EventedFd, Poll, Token as Token_06, Ready, PollOpt};
@@ -102,10 +99,66 @@ const IPPROTO_UDPLITE: c_int = 136;
 const UDPLITE_SEND_CSCOV: c_int = 10;
 #[cfg(any(target_os="linux", target_os="android"))]
 const UDPLITE_RECV_CSCOV: c_int = 11;
+#[cfg(all(any(target_os="linux", target_os="android"), not(target_arch="sparc64")))]
+const SO_MEMINFO: c_int = 55;
+#[cfg(all(any(target_os="linux", target_os="android"), target_arch="sparc64"))]
+const SO_MEMINFO: c_int = 0x39;
+#[cfg(all(any(target_os="linux", target_os="android"), not(target_arch="sparc64")))]
+const SO_RXQ_OVFL: c_int = 40;
+#[cfg(all(any(target_os="linux", target_os="android"), target_arch="sparc64"))]
+const SO_RXQ_OVFL: c_int = 0x24;
+#[cfg(all(any(target_os="linux", target_os="android"), not(target_arch="sparc64")))]
+const SO_TIMESTAMPING: c_int = 37;
+#[cfg(all(any(target_os="linux", target_os="android"), target_arch="sparc64"))]
+const SO_TIMESTAMPING: c_int = 0x23;
+#[cfg(any(target_os="linux", target_os="android"))]
+const SCM_TIMESTAMPING: c_int = SO_TIMESTAMPING;
+#[cfg(any(target_os="linux", target_os="android"))]
+const SO_EE_ORIGIN_TIMESTAMPING: u8 = 4;
+#[cfg(any(target_os="linux", target_os="android"))]
+const IPV6_FLOWLABEL_MGR: c_int = 32;
+#[cfg(any(target_os="linux", target_os="android"))]
+const IPV6_FLOWINFO_SEND: c_int = 33;
+#[cfg(any(target_os="linux", target_os="android"))]
+const IPV6_FL_A_GET: u8 = 0;
+#[cfg(any(target_os="linux", target_os="android"))]
+const IPV6_FL_S_EXCL: u8 = 1;
+#[cfg(any(target_os="linux", target_os="android"))]
+const IPV6_FL_F_CREATE: u16 = 1;
+#[cfg(any(target_os="linux", target_os="android"))]
+const IPV6_FL_F_EXCL: u16 = 2;
+#[cfg(any(target_os="linux", target_os="android"))]
+const SO_ATTACH_FILTER: c_int = 26;
+#[cfg(any(target_os="linux", target_os="android"))]
+const SO_DETACH_FILTER: c_int = 27;
+#[cfg(all(any(target_os="linux", target_os="android"), not(target_arch="sparc64")))]
+const SO_ATTACH_BPF: c_int = 50;
+#[cfg(all(any(target_os="linux", target_os="android"), target_arch="sparc64"))]
+const SO_ATTACH_BPF: c_int = 0x34;
+#[cfg(target_os="freebsd")]
+const MSG_WAITFORONE: c_int = 0x80000;
+#[cfg(target_os="freebsd")]
+const IP_RECVTTL: c_int = 65;
+#[cfg(target_os="freebsd")]
+const IPV6_RECVHOPLIMIT: c_int = 37;
+#[cfg(target_os="freebsd")]
+const IPV6_DONTFRAG: c_int = 62;
+#[cfg(target_os="freebsd")]
+const FIONWRITE: std::os::raw::c_ulong = 0x40046677;
+#[cfg(any(target_os="linux", target_os="android"))]
+const SK_MEMINFO_DROPS: usize = 8;
+#[cfg(any(target_os="linux", target_os="android"))]
+const SK_MEMINFO_VARS: usize = 9;
+#[cfg(not(target_os="freebsd"))]
+const IPV4_RECV_PKTINFO: c_int = IP_PKTINFO;
+#[cfg(target_os="freebsd")]
+const IPV4_RECV_PKTINFO: c_int = IP_RECVDSTADDR;
 #[cfg(target_os="freebsd")]
 const UDPLITE_SEND_CSCOV: c_int = 2;
 #[cfg(target_os="freebsd")]
 const UDPLITE_RECV_CSCOV: c_int = 4;
+/// `UIO_MAXIOV` on Linux and `IOV_MAX` on FreeBSD.
+const MAX_BATCH_SIZE: usize = 1024;
 
 
 
@@ -124,6 +177,53 @@ impl Debug for UdpLiteSocket {
     }
 }
 
+/// The `Debug` representation returned by `UdpLiteSocket.debug_verbose()`.
+struct VerboseDebug<'a>(&'a UdpLiteSocket);
+
+impl<'a> Debug for VerboseDebug<'a> {
+    fn fmt(&self,  fmtr: &mut fmt::Formatter) -> fmt::Result {
+        let mut repr = fmtr.debug_struct("UdpLiteSocket");
+        if let Ok(addr) = self.0.local_addr() {
+            repr.field("addr", &addr);
+        }
+        if let Ok(peer) = self.0.peer_addr() {
+            repr.field("peer", &peer);
+        }
+        repr.field("fd", &self.0.as_raw_fd());
+        // None means full coverage, so errors can't be shown as None
+        match self.0.send_checksum_coverage() {
+            Ok(send_cscov) => repr.field("send_cscov", &send_cscov),
+            Err(e) => repr.field("send_cscov", &e.kind()),
+        };
+        match self.0.recv_checksum_coverage_filter() {
+            Ok(recv_cscov) => repr.field("recv_cscov", &recv_cscov),
+            Err(e) => repr.field("recv_cscov", &e.kind()),
+        };
+        repr.finish()
+    }
+}
+
+impl Display for UdpLiteSocket {
+    fn fmt(&self,  fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fn coverage(coverage: Option<u16>) -> String {
+            match coverage {
+                Some(coverage) => coverage.to_string(),
+                None => String::from("full"),
+            }
+        }
+        match self.local_addr() {
+            Ok(addr) => write!(fmtr, "UDP-Lite {}", addr)?,
+            Err(_) => write!(fmtr, "UDP-Lite fd {}", self.as_raw_fd())?,
+        }
+        let send = self.send_checksum_coverage();
+        let recv = self.recv_checksum_coverage_filter();
+        if let (Ok(send), Ok(recv)) = (send, recv) {
+            write!(fmtr, " (send cscov={}, recv filter={})", coverage(send), coverage(recv))?;
+        }
+        Ok(())
+    }
+}
+
 impl FromRawFd for UdpLiteSocket {
     unsafe fn from_raw_fd(fd: RawFd) -> Self {
         UdpLiteSocket { as_udp: UdpSocket::from_raw_fd(fd) }
@@ -147,71 +247,757 @@ impl Deref for UdpLiteSocket {
     }
 }
 
-fn rust_addr_to_sockaddr(addr: &SocketAddr,  storage: &mut sockaddr_storage)
--> socklen_t {
+/// Receives one datagram from the connected peer per `read()`.
+///
+/// Datagrams longer than the buffer are truncated, and the rest of them
+/// is discarded; it's not returned by the next read.
+/// This makes it possible to pass a connected socket to APIs that take
+/// `impl Read`, but they must then handle datagram boundaries.
+impl Read for &UdpLiteSocket {
+    fn read(&mut self,  buf: &mut[u8]) -> Result<usize, io::Error> {
+        self.as_udp.recv(buf)
+    }
+}
+/// See the impl for `&UdpLiteSocket`.
+impl Read for UdpLiteSocket {
+    fn read(&mut self,  buf: &mut[u8]) -> Result<usize, io::Error> {
+        self.as_udp.recv(buf)
+    }
+}
+
+/// Sends each `write()` as one datagram to the connected peer.
+///
+/// Writes are never partial: a buffer too big for one datagram fails with
+/// `EMSGSIZE` instead. `flush()` does nothing.
+/// Beware of wrappers such as `BufWriter` or `write!()`, which can split or
+/// merge writes.
+impl Write for &UdpLiteSocket {
+    fn write(&mut self,  buf: &[u8]) -> Result<usize, io::Error> {
+        self.as_udp.send(buf)
+    }
+    fn flush(&mut self) -> Result<(), io::Error> {
+        Ok(())
+    }
+}
+/// See the impl for `&UdpLiteSocket`.
+impl Write for UdpLiteSocket {
+    fn write(&mut self,  buf: &[u8]) -> Result<usize, io::Error> {
+        self.as_udp.send(buf)
+    }
+    fn flush(&mut self) -> Result<(), io::Error> {
+        Ok(())
+    }
+}
+
+/// Call `getsockopt()` or `setsockopt()` until it isn't interrupted by a signal.
+fn sockopt_retry<F: FnMut() -> c_int>(mut call: F) -> Result<(), io::Error> {
+    loop {
+        if call() != -1 {
+            break Ok(());
+        }
+        let error = io::Error::last_os_error();
+        if error.kind() != Interrupted {
+            break Err(error);
+        }
+    }
+}
+
+/// `getsockopt()` for `int` options, retried if interrupted.
+fn get_int_option(fd: RawFd,  level: c_int,  name: c_int) -> Result<c_int, io::Error> {
+    let mut value: c_int = 0;
+    sockopt_retry(|| unsafe {
+        let mut len = mem::size_of::<c_int>() as socklen_t;
+        getsockopt(
+            fd,
+            level,
+            name,
+            &mut value as *mut c_int as *mut c_void,
+            &mut len as *mut socklen_t,
+        )
+    })?;
+    Ok(value)
+}
+
+/// `setsockopt()` for `int` options, retried if interrupted.
+fn set_int_option(fd: RawFd,  level: c_int,  name: c_int,  value: c_int)
+-> Result<(), io::Error> {
+    sockopt_retry(|| unsafe {
+        setsockopt(
+            fd,
+            level,
+            name,
+            &value as *const c_int as *const c_void,
+            mem::size_of::<c_int>() as socklen_t,
+        )
+    })
+}
+
+/// Call a send or receive function until it isn't interrupted by a signal,
+/// and convert its return value.
+///
+/// The sending and receiving methods this crate implements with raw
+/// syscalls go through this, so that they agree: `EINTR` is retried, and
+/// `EAGAIN` / `EWOULDBLOCK` (from non-blocking sockets, `MSG_DONTWAIT` or
+/// timeouts) becomes `ErrorKind::WouldBlock`.
+/// (The `Read` and `Write` impls and the methods from `UdpSocket` use std.)
+fn send_recv_retry<F: FnMut() -> isize>(mut call: F) -> Result<usize, io::Error> {
+    loop {
+        let ret = call();
+        if ret >= 0 {
+            break Ok(ret as usize);
+        }
+        // std maps both EAGAIN and EWOULDBLOCK to WouldBlock,
+        // and keeping the OS error preserves raw_os_error()
+        let error = io::Error::last_os_error();
+        if error.kind() != Interrupted {
+            break Err(error);
+        }
+    }
+}
+
+/// Call `send_from` with the number of the `len` datagrams to skip,
+/// and if it sends fewer than all, call it again with the rest to get the
+/// error `sendmmsg()` doesn't report after a partial send.
+fn send_batch<F: Fn(usize) -> Result<usize, io::Error>>(len: usize,  send_from: F)
+-> Result<usize, BatchSendError> {
+    let sent = send_from(0).map_err(|error| BatchSendError { index: 0,  error })?;
+    if sent == 0 || sent >= len {
+        return Ok(sent);
+    }
+    match send_from(sent) {
+        Ok(more) => Ok(sent + more),
+        Err(error) => Err(BatchSendError { index: sent,  error }),
+    }
+}
+
+/// Read the data of a control message, if it is big enough.
+#[allow(clippy::unnecessary_cast)] // cmsg_len is not size_t on musl
+fn cmsg_data<T: Copy>(cmsg: &cmsghdr) -> Option<T> {
+    unsafe {
+        if (cmsg.cmsg_len as usize) < CMSG_LEN(mem::size_of::<T>() as _) as usize {
+            return None;
+        }
+        Some(ptr::read_unaligned(CMSG_DATA(cmsg) as *const T))
+    }
+}
+
+/// Get the TOS or traffic class from an `IP_RECVTOS` or `IPV6_RECVTCLASS`
+/// control message.
+fn parse_tos_cmsg(cmsg: &cmsghdr) -> Option<u8> {
+    match (cmsg.cmsg_level, cmsg.cmsg_type) {
+        #[cfg(not(target_os="freebsd"))]
+        (IPPROTO_IP, IP_TOS) => cmsg_data::<u8>(cmsg),
+        #[cfg(target_os="freebsd")]
+        (IPPROTO_IP, IP_RECVTOS) => cmsg_data::<u8>(cmsg),
+        (IPPROTO_IPV6, IPV6_TCLASS) => cmsg_data::<c_int>(cmsg).map(|tclass| tclass as u8),
+        _ => None,
+    }
+}
+
+/// Get the address in an `IP_ORIGDSTADDR` or `IPV6_ORIGDSTADDR` control message.
+fn parse_sockaddr_cmsg(cmsg: &cmsghdr) -> Option<SocketAddr> {
+    let mut storage = unsafe { mem::zeroed::<sockaddr_storage>() };
+    let len = unsafe {
+        // cmsg_len is u32 on some platforms
+        let cmsg_len: usize = cmsg.cmsg_len as _;
+        let data_len = cmsg_len.saturating_sub(CMSG_LEN(0) as usize);
+        let len = data_len.min(mem::size_of::<sockaddr_storage>());
+        let dst = &mut storage as *mut sockaddr_storage as *mut u8;
+        ptr::copy_nonoverlapping(CMSG_DATA(cmsg), dst, len);
+        len
+    };
+    sockaddr_to_rust_addr(&storage, len as socklen_t).ok()
+}
+
+/// Store the metadata in a control message in `info`, if it's one
+/// [`recv_msg()`](struct.UdpLiteSocket.html#method.recv_msg) can request.
+fn parse_metadata_cmsg(cmsg: &cmsghdr,  info: &mut RecvInfo) {
+    if let Some(tos) = parse_tos_cmsg(cmsg) {
+        info.tos = Some(tos);
+        return;
+    }
+    match (cmsg.cmsg_level, cmsg.cmsg_type) {
+        #[cfg(not(target_os="freebsd"))]
+        (IPPROTO_IP, IP_TTL) => {
+            info.ttl = cmsg_data::<c_int>(cmsg).map(|ttl| ttl as u8);
+        }
+        #[cfg(target_os="freebsd")]
+        (IPPROTO_IP, IP_RECVTTL) => {
+            info.ttl = cmsg_data::<u8>(cmsg);
+        }
+        (IPPROTO_IPV6, IPV6_HOPLIMIT) => {
+            info.ttl = cmsg_data::<c_int>(cmsg).map(|hop_limit| hop_limit as u8);
+        }
+        #[cfg(not(target_os="freebsd"))]
+        (IPPROTO_IP, IP_PKTINFO) => {
+            if let Some(pktinfo) = cmsg_data::<in_pktinfo>(cmsg) {
+                let dest = Ipv4Addr::from(u32::from_be(pktinfo.ipi_addr.s_addr));
+                info.dest = Some(IpAddr::V4(dest));
+                info.ifindex = Some(pktinfo.ipi_ifindex as u32);
+            }
+        }
+        #[cfg(target_os="freebsd")]
+        (IPPROTO_IP, IP_RECVDSTADDR) => {
+            if let Some(dest) = cmsg_data::<in_addr>(cmsg) {
+                info.dest = Some(IpAddr::V4(Ipv4Addr::from(u32::from_be(dest.s_addr))));
+            }
+        }
+        (IPPROTO_IPV6, IPV6_PKTINFO) => {
+            if let Some(pktinfo) = cmsg_data::<in6_pktinfo>(cmsg) {
+                info.dest = Some(IpAddr::V6(Ipv6Addr::from(pktinfo.ipi6_addr.s6_addr)));
+                info.ifindex = Some(pktinfo.ipi6_ifindex);
+            }
+        }
+        (IPPROTO_IP, IP_ORIGDSTADDR) | (IPPROTO_IPV6, IPV6_ORIGDSTADDR) => {
+            info.orig_dst = parse_sockaddr_cmsg(cmsg);
+        }
+        #[cfg(any(target_os="linux", target_os="android"))]
+        (SOL_SOCKET, SO_RXQ_OVFL) => {
+            info.dropped = cmsg_data::<u32>(cmsg);
+        }
+        (SOL_SOCKET, SCM_TIMESTAMP) => {
+            if let Some(time) = cmsg_data::<timeval>(cmsg) {
+                let since_epoch = Duration::new(time.tv_sec as u64, time.tv_usec as u32 * 1000);
+                info.timestamp = Some(UNIX_EPOCH + since_epoch);
+            }
+        }
+        #[cfg(any(target_os="linux", target_os="android"))]
+        (SOL_SOCKET, SCM_TIMESTAMPING) => {
+            if let Some([software, _, hardware]) = cmsg_data::<[timespec; 3]>(cmsg) {
+                // SO_TIMESTAMP might also be enabled, and is the same time
+                info.timestamp = info.timestamp.or_else(|| timespec_to_system_time(software));
+                info.hardware_timestamp = timespec_to_system_time(hardware);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Convert a timestamp from `SCM_TIMESTAMPING`, where all zeroes means
+/// that the timestamp isn't available.
+#[cfg(any(target_os="linux", target_os="android"))]
+fn timespec_to_system_time(time: timespec) -> Option<SystemTime> {
+    if time.tv_sec == 0 && time.tv_nsec == 0 {
+        return None;
+    }
+    Some(UNIX_EPOCH + Duration::new(time.tv_sec as u64, time.tv_nsec as u32))
+}
+
+/// Compute the internet checksum of `data`, as used by UDP-Lite.
+#[cfg(feature="raw_coverage")]
+fn internet_checksum(data: &[u8]) -> u16 {
+    let mut sum = data.chunks(2)
+        .map(|pair| u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)]) as u32)
+        .fold(0u32, |sum, word| sum.wrapping_add(word));
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// `struct sock_extended_err` from `linux/errqueue.h`.
+#[cfg(any(target_os="linux", target_os="android"))]
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct SockExtendedErr {
+    errno: u32,
+    origin: u8,
+    kind: u8,
+    code: u8,
+    _pad: u8,
+    info: u32,
+    data: u32,
+}
+
+/// An entry in the error queue of a socket, received by
+/// [`recv_error()`](struct.UdpLiteSocket.html#method.recv_error).
+///
+/// Entries are either errors (if enabled with `IP_RECVERR` or
+/// `IPV6_RECVERR`) or transmit timestamps (if enabled with
+/// [`set_timestamping()`](struct.UdpLiteSocket.html#method.set_timestamping)).
+#[cfg(any(target_os="linux", target_os="android"))]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[non_exhaustive]
+pub struct ErrorQueueEntry {
+    /// How much of the sent packet was copied into the buffer.
+    ///
+    /// This is zero for timestamps with `SOF_TIMESTAMPING_OPT_TSONLY`.
+    pub len: usize,
+    /// The address the datagram was sent to, if the OS provided it.
+    pub dest: Option<SocketAddr>,
+    /// The error number of an error, or 0 for timestamps.
+    pub errno: i32,
+    /// The ID of the sent datagram the timestamps are for.
+    ///
+    /// With `SOF_TIMESTAMPING_OPT_ID` the OS counts sent datagrams from 0
+    /// when timestamping is enabled, and this is the number of the datagram,
+    /// which correlates the timestamp with the send it is for.
+    /// Without that flag it's always `Some(0)` for timestamps.
+    pub tx_id: Option<u32>,
+    /// Which point of the transmission the timestamps are from,
+    /// as one of the `SCM_TSTAMP_*` constants:
+    /// `SCM_TSTAMP_SND` (0) when the datagram was handed to the network
+    /// card, or `SCM_TSTAMP_SCHED` (1) when it entered the queueing discipline.
+    pub tx_type: Option<u32>,
+    /// The software transmit timestamp, from `SOF_TIMESTAMPING_TX_SOFTWARE`
+    /// or `SOF_TIMESTAMPING_TX_SCHED`.
+    pub tx_timestamp: Option<SystemTime>,
+    /// When the network card sent the datagram, from
+    /// `SOF_TIMESTAMPING_TX_HARDWARE`, in the clock of the network card.
+    pub tx_hardware_timestamp: Option<SystemTime>,
+}
+
+/// An instruction of a classic BPF program, for
+/// [`attach_filter()`](struct.UdpLiteSocket.html#method.attach_filter).
+///
+/// This has the same layout as `struct sock_filter` from `linux/filter.h`.
+#[cfg(any(target_os="linux", target_os="android"))]
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct SockFilter {
+    /// The operation, as a combination of `BPF_*` constants.
+    pub code: u16,
+    /// How many instructions to skip forward if a conditional jump is taken.
+    pub jt: u8,
+    /// How many instructions to skip forward if a conditional jump is not taken.
+    pub jf: u8,
+    /// The immediate operand of the instruction.
+    pub k: u32,
+}
+
+#[cfg(any(target_os="linux", target_os="android"))]
+impl SockFilter {
+    /// Create an instruction, like the `BPF_JUMP()` macro, or `BPF_STMT()`
+    /// if `jt` and `jf` are zero.
+    pub const fn new(code: u16,  jt: u8,  jf: u8,  k: u32) -> Self {
+        SockFilter { code, jt, jf, k }
+    }
+}
+
+/// `struct sock_fprog` from `linux/filter.h`, for `SO_ATTACH_FILTER`.
+#[cfg(any(target_os="linux", target_os="android"))]
+#[repr(C)]
+struct SockFprog {
+    len: u16,
+    filter: *const SockFilter,
+}
+
+/// Create a `recvmmsg()` header that receives the source address into `addr`.
+fn recv_mmsghdr(addr: &mut sockaddr_storage,  iov: *mut iovec,  iovlen: usize) -> mmsghdr {
+    // zero padding fields on musl
+    let mut header = unsafe { mem::zeroed::<mmsghdr>() };
+    header.msg_hdr.msg_name = addr as *mut sockaddr_storage as *mut c_void;
+    header.msg_hdr.msg_namelen = mem::size_of::<sockaddr_storage>() as socklen_t;
+    header.msg_hdr.msg_iov = iov;
+    header.msg_hdr.msg_iovlen = iovlen as _;
+    header
+}
+
+/// Reusable buffers for receiving multiple datagrams with
+/// [`recv_batch()`](struct.UdpLiteSocket.html#method.recv_batch).
+///
+/// This holds the datagrams, their source addresses and the headers passed
+/// to `recvmmsg()`, so that batched receives don't need to allocate.
+pub struct BatchBuffer {
+    data: Vec<u8>,
+    max_len: usize,
+    addrs: Vec<sockaddr_storage>,
+    iovecs: Vec<iovec>,
+    headers: Vec<mmsghdr>,
+    received: Vec<(usize, SocketAddr)>,
+}
+
+// The pointers in iovecs and headers point into data and addrs,
+// and are rewritten before each use.
+unsafe impl Send for BatchBuffer {}
+unsafe impl Sync for BatchBuffer {}
+
+impl BatchBuffer {
+    /// Create buffers for receiving up to `datagrams` datagrams of at most
+    /// `max_len` bytes each at once.
+    ///
+    /// # Panics
+    ///
+    /// If the total size overflows `usize`.
+    pub fn new(datagrams: usize,  max_len: usize) -> Self {
+        let total = datagrams.checked_mul(max_len).expect("BatchBuffer is too big");
+        // safe because they don't store any fancy Rust types
+        BatchBuffer {
+            data: vec![0; total],
+            max_len,
+            addrs: vec![unsafe { mem::zeroed::<sockaddr_storage>() }; datagrams],
+            iovecs: vec![unsafe { mem::zeroed::<iovec>() }; datagrams],
+            headers: vec![unsafe { mem::zeroed::<mmsghdr>() }; datagrams],
+            received: Vec::with_capacity(datagrams),
+        }
+    }
+
+    /// The maximum number of datagrams that can be received at once.
+    pub fn datagrams(&self) -> usize {
+        self.addrs.len()
+    }
+
+    /// The length of the buffer for each datagram.
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+
+    fn iter(&self) -> impl Iterator<Item=(&[u8], SocketAddr)> {
+        let (data, max_len) = (&self.data, self.max_len);
+        self.received.iter().enumerate().map(move |(i, &(len, from))| {
+            (&data[i*max_len..][..len.min(max_len)], from)
+        })
+    }
+}
+
+impl Debug for BatchBuffer {
+    fn fmt(&self,  fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.debug_struct("BatchBuffer")
+            .field("datagrams", &self.datagrams())
+            .field("max_len", &self.max_len)
+            .field("received", &self.received.len())
+            .finish()
+    }
+}
+
+/// Get the protocol of a socket. (`SO_PROTOCOL`)
+///
+/// If the OS doesn't support that option, UDP-Lite sockets are recognized by
+/// supporting the UDP-Lite-level checksum coverage option instead.
+fn get_protocol(fd: RawFd) -> Result<c_int, io::Error> {
+    match get_int_option(fd, SOL_SOCKET, SO_PROTOCOL) {
+        Err(ref e) if e.raw_os_error() == Some(ENOPROTOOPT) => {
+            match get_int_option(fd, IPPROTO_UDPLITE, UDPLITE_SEND_CSCOV) {
+                Ok(_) => Ok(IPPROTO_UDPLITE),
+                Err(_) => Err(io::Error::from_raw_os_error(ENOPROTOOPT)),
+            }
+        }
+        result => result,
+    }
+}
+
+/// Look up the index of a network interface. (`if_nametoindex()`)
+fn interface_index(name: &str) -> Result<u32, io::Error> {
+    let name = CString::new(name)
+        .map_err(|_| io::Error::new(InvalidInput, "Interface name contains NUL"))?;
+    match unsafe { if_nametoindex(name.as_ptr()) } {
+        0 => Err(io::Error::new(NotFound, "No network interface with that name")),
+        index => Ok(index),
+    }
+}
+
+/// Find the (first) IPv4 address of a network interface. (`getifaddrs()`)
+fn interface_ipv4(name: &str) -> Result<Ipv4Addr, io::Error> {
+    let mut list = ptr::null_mut::<ifaddrs>();
+    if unsafe { getifaddrs(&mut list) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut found_interface = false;
+    let mut found_addr = None;
+    let mut current = list;
+    while !current.is_null() {
+        unsafe {
+            let interface = &*current;
+            current = interface.ifa_next;
+            if CStr::from_ptr(interface.ifa_name).to_bytes() != name.as_bytes() {
+                continue;
+            }
+            found_interface = true;
+            let addr = interface.ifa_addr;
+            if !addr.is_null() && (*addr).sa_family as c_int == AF_INET {
+                let addr = &*(addr as *const sockaddr_in);
+                found_addr = Some(Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr)));
+                break;
+            }
+        }
+    }
+    unsafe { freeifaddrs(list) };
+    match (found_addr, found_interface) {
+        (Some(addr), _) => Ok(addr),
+        (None, true) => Err(io::Error::new(AddrNotAvailable, "Network interface has no IPv4 address")),
+        (None, false) => Err(io::Error::new(NotFound, "No network interface with that name")),
+    }
+}
+
+/// Check that a file descriptor is a UDP-Lite socket.
+fn check_is_udplite(fd: RawFd) -> Result<(), io::Error> {
+    if get_int_option(fd, SOL_SOCKET, SO_TYPE)? != SOCK_DGRAM {
+        Err(io::Error::new(InvalidInput, "Socket is not a datagram socket"))
+    } else if get_protocol(fd)? != IPPROTO_UDPLITE {
+        Err(io::Error::new(InvalidInput, "Socket is not UDP-Lite"))
+    } else {
+        Ok(())
+    }
+}
+
+/// An IPv4 or IPv6 `sockaddr` which, unlike `sockaddr_storage`,
+/// only needs to be as big as the biggest of those.
+#[derive(Clone, Copy)]
+#[repr(C)]
+union SockAddrIn {
+    v4: sockaddr_in,
+    v6: sockaddr_in6,
+}
+
+impl SockAddrIn {
+    fn as_ptr(&self) -> *const sockaddr {
+        self as *const SockAddrIn as *const sockaddr
+    }
+}
+
+/// Returns the OS representation of the address and its exact length.
+fn rust_addr_to_sockaddr(addr: &SocketAddr) -> (SockAddrIn, socklen_t) {
     match addr {
         SocketAddr::V4(addrv4) => {
-            storage.ss_family = AF_INET as sa_family_t;
-            // shadow to avoid aliasing
+            // zero padding and sin_len
+            let mut sin = unsafe { mem::zeroed::<sockaddr_in>() };
+            sin.sin_family = AF_INET as sa_family_t;
+            sin.sin_addr.s_addr = u32::from(*addrv4.ip()).to_be();
+            sin.sin_port = addrv4.port().to_be();
+            (SockAddrIn { v4: sin }, mem::size_of::<sockaddr_in>() as socklen_t)
+        }
+        SocketAddr::V6(addrv6) => {
+            let mut sin6 = unsafe { mem::zeroed::<sockaddr_in6>() };
+            sin6.sin6_family = AF_INET6 as sa_family_t;
+            sin6.sin6_port = addrv6.port().to_be();
+            sin6.sin6_flowinfo = addrv6.flowinfo();
+            sin6.sin6_addr.s6_addr = addrv6.ip().octets();
+            sin6.sin6_scope_id = addrv6.scope_id();
+            (SockAddrIn { v6: sin6 }, mem::size_of::<sockaddr_in6>() as socklen_t)
+        }
+    }
+}
+
+/// Check whether the OS supports UDP-Lite sockets.
+///
+/// This creates and immediately closes an unbound UDP-Lite socket,
+/// and returns whether that succeeded.
+/// Some kernels are built without UDP-Lite, and creating sockets then fails
+/// with `EPROTONOSUPPORT`, so this can be used to decide whether to fall back
+/// to plain UDP at startup.
+pub fn is_supported() -> bool {
+    // IPv4 or IPv6 might be disabled, so try both
+    for &domain in &[AF_INET, AF_INET6] {
+        unsafe {
+            let fd = socket(domain, SOCK_DGRAM | SOCK_CLOEXEC, IPPROTO_UDPLITE);
+            if fd != -1 {
+                close(fd);
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// The maximum number of datagrams that can be sent or received with one
+/// system call by the batch methods.
+///
+/// The kernel silently limits `recvmmsg()` and `sendmmsg()` to this many
+/// messages, so [`recv_many()`](struct.UdpLiteSocket.html#method.recv_many),
+/// [`recv_batch()`](struct.UdpLiteSocket.html#method.recv_batch),
+/// [`send_many()`](struct.UdpLiteSocket.html#method.send_many) and
+/// [`send_to_many()`](struct.UdpLiteSocket.html#method.send_to_many)
+/// fail with `InvalidInput` instead of when given more.
+pub fn max_batch_size() -> usize {
+    MAX_BATCH_SIZE
+}
+
+/// Fail with `InvalidInput` for batches the kernel would truncate.
+fn check_batch_size(len: usize) -> Result<(), io::Error> {
+    if len > MAX_BATCH_SIZE {
+        Err(io::Error::new(InvalidInput, "Too many datagrams for one system call"))
+    } else {
+        Ok(())
+    }
+}
+
+/// What the kernel reports about UDP-Lite, returned by
+/// [`kernel_info()`](fn.kernel_info.html).
+#[cfg(any(target_os="linux", target_os="android"))]
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[non_exhaustive]
+pub struct UdpLiteKernelInfo {
+    /// Whether the kernel supports UDP-Lite over IPv4, from whether
+    /// `/proc/net/udplite` exists.
+    pub ipv4: bool,
+    /// Whether the kernel supports UDP-Lite over IPv6, from whether
+    /// `/proc/net/udplite6` exists.
+    pub ipv6: bool,
+    /// The IPv4 UDP-Lite counters in `/proc/net/snmp`, such as
+    /// `InDatagrams` and `InErrors`, in the order listed there.
+    ///
+    /// Datagrams discarded by a checksum coverage filter count as `InErrors`.
+    pub counters: Vec<(String, u64)>,
+    /// The IPv6 UDP-Lite counters in `/proc/net/snmp6`, with the same names
+    /// as in `counters` (without the `UdpLite6` prefix).
+    pub counters6: Vec<(String, u64)>,
+    /// The minimum receive buffer size, shared with UDP.
+    /// (`net.ipv4.udp_rmem_min`)
+    pub rmem_min: Option<u64>,
+    /// The minimum send buffer size, shared with UDP.
+    /// (`net.ipv4.udp_wmem_min`)
+    pub wmem_min: Option<u64>,
+}
+
+/// Read what the kernel reports about UDP-Lite from `/proc`.
+///
+/// This is for startup checks and bug reports.
+/// There are no sysctls that limit checksum coverage, so when a coverage
+/// setting doesn't seem to have an effect, compare the `InErrors` counter
+/// before and after.
+/// UDP-Lite is part of the IPv4 and IPv6 code in the kernel and not a
+/// separate module, so it's only missing if the kernel was compiled without it.
+///
+/// This function is Linux-specific.
+///
+/// # Errors
+///
+/// Fails if `/proc/net/snmp` can't be read, which happens if `/proc`
+/// isn't mounted.
+/// Missing IPv6 counters or sysctls are not errors.
+#[cfg(any(target_os="linux", target_os="android"))]
+pub fn kernel_info() -> Result<UdpLiteKernelInfo, io::Error> {
+    use std::fs;
+    use std::path::Path;
+    let read_sysctl = |name: &str| {
+        fs::read_to_string(Path::new("/proc/sys/net/ipv4").join(name)).ok()
+            .and_then(|value| value.trim().parse().ok())
+    };
+    // header line followed by values line, both starting with "UdpLite:"
+    let snmp = fs::read_to_string("/proc/net/snmp")?;
+    let mut lines = snmp.lines().filter_map(|line| line.strip_prefix("UdpLite:"));
+    let counters = match (lines.next(), lines.next()) {
+        (Some(names), Some(values)) => names.split_whitespace()
+            .zip(values.split_whitespace())
+            .filter_map(|(name, value)| Some((name.to_owned(), value.parse().ok()?)))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let snmp6 = fs::read_to_string("/proc/net/snmp6").unwrap_or_default();
+    let counters6 = snmp6.lines()
+        .filter_map(|line| line.strip_prefix("UdpLite6"))
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            Some((fields.next()?.to_owned(), fields.next()?.parse().ok()?))
+        })
+        .collect();
+    Ok(UdpLiteKernelInfo {
+        ipv4: Path::new("/proc/net/udplite").exists(),
+        ipv6: Path::new("/proc/net/udplite6").exists(),
+        counters,
+        counters6,
+        rmem_min: read_sysctl("udp_rmem_min"),
+        wmem_min: read_sysctl("udp_wmem_min"),
+    })
+}
+
+/// Convert an IPv4 or IPv6 address returned by the OS into a `SocketAddr`.
+///
+/// `len` is the address length returned by the OS, and must not be shorter
+/// than the `sockaddr_in` or `sockaddr_in6` indicated by `ss_family`.
+/// For IPv6 addresses, flow info and scope ID are preserved.
+///
+/// # Errors
+///
+/// Fails with `InvalidData` if the address family is neither `AF_INET` nor
+/// `AF_INET6`, or if `len` is too short.
+pub fn sockaddr_to_rust_addr(storage: &sockaddr_storage,  len: socklen_t)
+-> Result<SocketAddr, io::Error> {
+    match storage.ss_family as c_int {
+        AF_INET if len as usize >= mem::size_of::<sockaddr_in>() => {
             let storage = unsafe {
-                &mut*{storage as *mut sockaddr_storage as *mut sockaddr_in}
+                &*{storage as *const sockaddr_storage as *const sockaddr_in}
             };
-            storage.sin_addr.s_addr = u32::from(*addrv4.ip()).to_be();
-            storage.sin_port = addrv4.port().to_be();
-            mem::size_of::<sockaddr_in>() as socklen_t
+            let ip = Ipv4Addr::from(u32::from_be(storage.sin_addr.s_addr));
+            let port = u16::from_be(storage.sin_port);
+            Ok(SocketAddr::V4(SocketAddrV4::new(ip, port)))
         }
-        SocketAddr::V6(addrv6) => {
-            storage.ss_family = AF_INET6 as sa_family_t;
+        AF_INET6 if len as usize >= mem::size_of::<sockaddr_in6>() => {
             let storage = unsafe {
-                &mut*{storage as *mut sockaddr_storage as *mut sockaddr_in6}
+                &*{storage as *const sockaddr_storage as *const sockaddr_in6}
             };
-            storage.sin6_port = addrv6.port().to_be();
-            storage.sin6_flowinfo = addrv6.flowinfo();
-            storage.sin6_addr.s6_addr = addrv6.ip().octets();
-            storage.sin6_scope_id = addrv6.scope_id();
-            mem::size_of::<sockaddr_in6>() as socklen_t
+            let ip = Ipv6Addr::from(storage.sin6_addr.s6_addr);
+            let port = u16::from_be(storage.sin6_port);
+            Ok(SocketAddr::V6(SocketAddrV6::new(
+                ip,
+                port,
+                storage.sin6_flowinfo,
+                storage.sin6_scope_id,
+            )))
         }
+        AF_INET | AF_INET6 => Err(io::Error::new(InvalidData, "Address is too short")),
+        _ => Err(io::Error::new(InvalidData, "Address is neither IPv4 nor IPv6")),
     }
 }
 
-fn try_bind(addr: &SocketAddr,  nonblocking: bool)
--> Result<UdpLiteSocket, io::Error> {
-    // safe because it doesn't store any fancy Rust types
-    let mut storage = unsafe { mem::zeroed::<sockaddr_storage>() };
-    let addr_len = rust_addr_to_sockaddr(&addr, &mut storage);
-    let addr_type = storage.ss_family as c_int;
-    let sock = unsafe {
-        let mut flags = SOCK_CLOEXEC;
-        if nonblocking {
-            flags |= SOCK_NONBLOCK;
-        }
-        match socket(addr_type, SOCK_DGRAM | flags, IPPROTO_UDPLITE) {
-            -1 => return Err(io::Error::last_os_error()),
-            fd => UdpLiteSocket::from_raw_fd(fd),
-        }
-    };
-    unsafe {
-        let general_ptr = &storage as *const sockaddr_storage as *const sockaddr;
-        loop {
-            if bind(sock.as_raw_fd(), general_ptr, addr_len) == -1 {
-                let error = io::Error::last_os_error();
-                if error.kind() != Interrupted {
-                    break Err(error);
-                }
+/// Create an unbound UDP-Lite socket.
+fn create_socket(domain: c_int,  nonblocking: bool) -> Result<UdpLiteSocket, io::Error> {
+    let mut flags = SOCK_CLOEXEC;
+    if nonblocking {
+        flags |= SOCK_NONBLOCK;
+    }
+    match unsafe { socket(domain, SOCK_DGRAM | flags, IPPROTO_UDPLITE) } {
+        -1 => {
+            let error = io::Error::last_os_error();
+            if error.raw_os_error() == Some(EPROTONOSUPPORT) {
+                Err(io::Error::new(Unsupported, "The kernel doesn't support UDP-Lite"))
             } else {
-                break Ok(sock);
+                Err(error)
+            }
+        }
+        fd => Ok(unsafe { UdpLiteSocket::from_raw_fd(fd) }),
+    }
+}
+
+/// Bind a socket, retrying if interrupted.
+fn bind_socket(sock: &UdpLiteSocket,  addr: &SocketAddr) -> Result<(), io::Error> {
+    let (raw_addr, addr_len) = rust_addr_to_sockaddr(addr);
+    loop {
+        if unsafe { bind(sock.as_raw_fd(), raw_addr.as_ptr(), addr_len) } == -1 {
+            let error = io::Error::last_os_error();
+            if error.kind() != Interrupted {
+                break Err(error);
             }
+        } else {
+            break Ok(());
         }
     }
 }
 
+fn try_bind(addr: &SocketAddr,  nonblocking: bool)
+-> Result<UdpLiteSocket, io::Error> {
+    let addr_type = match addr {
+        SocketAddr::V4(_) => AF_INET,
+        SocketAddr::V6(_) => AF_INET6,
+    };
+    let sock = create_socket(addr_type, nonblocking)?;
+    if let Err(error) = bind_socket(&sock, addr) {
+        // close the socket here instead of relying on it going out of scope
+        drop(sock);
+        return Err(error);
+    }
+    Ok(sock)
+}
+
 impl UdpLiteSocket {
+    /// The lowest possible checksum coverage, which covers only the 8-byte
+    /// UDP-Lite header.
+    ///
+    /// Checksum coverage in this crate is counted in bytes of payload,
+    /// so this is zero. (The checksum coverage field sent on the wire
+    /// includes the header, and is 8 for this value.)
+    /// The header is always covered.
+    pub const MIN_COVERAGE: u16 = 0;
+
     /// Create a blocking UDP-Lite socket bound to an address and port.
+    ///
+    /// # Errors
+    ///
+    /// If the kernel was built without UDP-Lite, this fails with
+    /// `ErrorKind::Unsupported` (instead of `EPROTONOSUPPORT`),
+    /// and so do all other methods that create sockets.
     pub fn bind<A: ToSocketAddrs>(addrs: A) -> Result<Self, io::Error> {
-        let addrs = match addrs.to_socket_addrs() {
-            Ok(iterator) => iterator,
-            Err(error) => return Err(error)
-        };
+        let addrs = addrs.to_socket_addrs()?;
         let mut error = io::Error::new(InvalidInput, "could not resolve to any addresses");
         for addr in addrs {
             match try_bind(&addr, false) {
@@ -222,15 +1008,24 @@ impl UdpLiteSocket {
         Err(error)
     }
 
-    /// Create a non-blocking UDP-Lite socket bound to an address and port.
-    pub fn bind_nonblocking<A: ToSocketAddrs>(addrs: A) -> Result<Self, io::Error> {
-        let addrs = match addrs.to_socket_addrs() {
-            Ok(iterator) => iterator,
-            Err(error) => return Err(error)
-        };
+    /// Create a blocking UDP-Lite socket bound to an address and port,
+    /// trying addresses of the preferred family first.
+    ///
+    /// This is for host names that resolve to both IPv4 and IPv6 addresses:
+    /// [`bind()`](#method.bind) tries them in the order returned by the
+    /// resolver. Here addresses of `prefer` are tried first, in that order,
+    /// and then the others if none of them could be bound.
+    /// With `None` this is the same as `bind()`.
+    pub fn bind_prefer<A: ToSocketAddrs>(addrs: A,  prefer: Option<AddressFamily>)
+    -> Result<Self, io::Error> {
+        let mut addrs = addrs.to_socket_addrs()?.collect::<Vec<SocketAddr>>();
+        if let Some(prefer) = prefer {
+            // stable, so the resolver's order is kept within each family
+            addrs.sort_by_key(|addr| AddressFamily::from(addr) != prefer);
+        }
         let mut error = io::Error::new(InvalidInput, "could not resolve to any addresses");
         for addr in addrs {
-            match try_bind(&addr, true) {
+            match try_bind(&addr, false) {
                 Err(e) => error = e,
                 ok => return ok,
             }
@@ -238,25 +1033,311 @@ impl UdpLiteSocket {
         Err(error)
     }
 
-    pub fn try_clone(&self) -> Result<Self, io::Error> {
-        match self.as_udp.try_clone() {
-            Ok(clone) => Ok(UdpLiteSocket { as_udp: clone }),
-            Err(e) => Err(e),
-        }
+    /// Create a blocking IPv4 UDP-Lite socket bound to an address and port,
+    /// ignoring any IPv6 addresses `addrs` resolves to.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `InvalidInput` if `addrs` doesn't resolve to any IPv4
+    /// addresses, and otherwise like [`bind()`](#method.bind).
+    pub fn bind_v4<A: ToSocketAddrs>(addrs: A) -> Result<Self, io::Error> {
+        Self::bind_family(addrs, AddressFamily::Ipv4)
     }
 
-    // send_cscov(,) -> Result<u16>
-    // set_send_cscov(, u16) -> Result<()>
-    // send(, &[u8], SocketAddr) -
-    // send_vectored
-    // send_to()
-    // send_many
-    // 
+    /// Create a blocking IPv6 UDP-Lite socket bound to an address and port,
+    /// ignoring any IPv4 addresses `addrs` resolves to.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `InvalidInput` if `addrs` doesn't resolve to any IPv6
+    /// addresses, and otherwise like [`bind()`](#method.bind).
+    pub fn bind_v6<A: ToSocketAddrs>(addrs: A) -> Result<Self, io::Error> {
+        Self::bind_family(addrs, AddressFamily::Ipv6)
+    }
+
+    fn bind_family<A: ToSocketAddrs>(addrs: A,  family: AddressFamily)
+    -> Result<Self, io::Error> {
+        let addrs = addrs.to_socket_addrs()?
+            .filter(|addr| AddressFamily::from(addr) == family);
+        let message = match family {
+            AddressFamily::Ipv4 => "could not resolve to any IPv4 addresses",
+            AddressFamily::Ipv6 => "could not resolve to any IPv6 addresses",
+        };
+        let mut error = io::Error::new(InvalidInput, message);
+        for addr in addrs {
+            match try_bind(&addr, false) {
+                Err(e) => error = e,
+                ok => return ok,
+            }
+        }
+        Err(error)
+    }
+
+    /// Create a blocking UDP-Lite socket bound to a specific address and port.
+    ///
+    /// Unlike [`bind()`](#method.bind) this takes a `SocketAddr` directly,
+    /// so it never does DNS lookups (which block) and doesn't allocate.
+    pub fn bind_addr(addr: SocketAddr) -> Result<Self, io::Error> {
+        try_bind(&addr, false)
+    }
+
+    /// Create a non-blocking UDP-Lite socket bound to an address and port.
+    pub fn bind_nonblocking<A: ToSocketAddrs>(addrs: A) -> Result<Self, io::Error> {
+        let addrs = addrs.to_socket_addrs()?;
+        let mut error = io::Error::new(InvalidInput, "could not resolve to any addresses");
+        for addr in addrs {
+            match try_bind(&addr, true) {
+                Err(e) => error = e,
+                ok => return ok,
+            }
+        }
+        Err(error)
+    }
+
+    /// Create a blocking UDP-Lite socket that is not bound yet.
+    ///
+    /// This is for setting options that only have an effect when binding,
+    /// such as [`set_freebind()`](#method.set_freebind);
+    /// bind it afterwards with [`bind_to()`](#method.bind_to).
+    /// Sending from an unbound socket makes the OS bind it to a random port.
+    pub fn new_unbound(family: AddressFamily) -> Result<Self, io::Error> {
+        match family {
+            AddressFamily::Ipv4 => create_socket(AF_INET, false),
+            AddressFamily::Ipv6 => create_socket(AF_INET6, false),
+        }
+    }
+
+    /// Bind a socket created with [`new_unbound()`](#method.new_unbound)
+    /// to an address and port.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `InvalidInput` if the socket is already bound.
+    pub fn bind_to(&self,  addr: SocketAddr) -> Result<(), io::Error> {
+        bind_socket(self, &addr)
+    }
+
+    /// Create a socket, set the options in `config` and then bind it.
+    ///
+    /// Options that are `None` or `false` are left at the OS default,
+    /// so a default config creates the same socket as
+    /// [`bind()`](#method.bind) does.
+    /// Reuse options are set before binding and the rest after.
+    ///
+    /// This method requires the `serde` feature.
+    ///
+    /// # Errors
+    ///
+    /// Buffer sizes above `i32::MAX` fail with `InvalidInput`, before any
+    /// socket is created.
+    /// Otherwise the first error from creating, configuring or binding
+    /// the socket is returned.
+    #[cfg(feature="serde")]
+    pub fn from_config(config: &UdpLiteConfig) -> Result<Self, io::Error> {
+        fn buffer_size(size: usize) -> Result<c_int, io::Error> {
+            if size > c_int::MAX as usize {
+                Err(io::Error::new(InvalidInput, "Buffer size is too big"))
+            } else {
+                Ok(size as c_int)
+            }
+        }
+        let recv_buffer_size = config.recv_buffer_size.map(buffer_size).transpose()?;
+        let send_buffer_size = config.send_buffer_size.map(buffer_size).transpose()?;
+        let domain = match config.bind_addr {
+            SocketAddr::V4(_) => AF_INET,
+            SocketAddr::V6(_) => AF_INET6,
+        };
+        let sock = create_socket(domain, config.nonblocking)?;
+        let fd = sock.as_raw_fd();
+        if config.reuse_address {
+            set_int_option(fd, SOL_SOCKET, SO_REUSEADDR, 1)?;
+        }
+        if config.reuse_port {
+            set_int_option(fd, SOL_SOCKET, SO_REUSEPORT, 1)?;
+        }
+        bind_socket(&sock, &config.bind_addr)?;
+        if let Some(coverage) = config.send_coverage {
+            sock.set_send_checksum_coverage(Some(coverage))?;
+        }
+        if let Some(filter) = config.recv_coverage_filter {
+            sock.set_recv_checksum_coverage_filter(Some(filter))?;
+        }
+        if let Some(size) = recv_buffer_size {
+            set_int_option(fd, SOL_SOCKET, SO_RCVBUF, size)?;
+        }
+        if let Some(size) = send_buffer_size {
+            set_int_option(fd, SOL_SOCKET, SO_SNDBUF, size)?;
+        }
+        Ok(sock)
+    }
+
+    /// Create two UDP-Lite sockets bound to loopback and connected to each other.
+    ///
+    /// The sockets are bound to ephemeral ports on `127.0.0.1`,
+    /// or on `::1` if IPv4 isn't available.
+    /// This is mainly useful for tests and in-process pipelines.
+    pub fn pair() -> Result<(Self, Self), io::Error> {
+        let v4 = SocketAddr::from((Ipv4Addr::LOCALHOST, 0));
+        let v6 = SocketAddr::from((Ipv6Addr::LOCALHOST, 0));
+        let a = Self::bind(&[v4, v6][..])?;
+        let b = Self::bind(SocketAddr::new(a.local_addr()?.ip(), 0))?;
+        a.connect(b.local_addr()?)?;
+        b.connect(a.local_addr()?)?;
+        Ok((a, b))
+    }
+
+    /// Create an IPv6 socket that also accepts IPv4 datagrams,
+    /// bound to `[::]:port`.
+    ///
+    /// This disables `IPV6_V6ONLY` before binding. IPv4 peers then show up
+    /// as IPv4-mapped IPv6 addresses (`::ffff:a.b.c.d`), and datagrams to
+    /// IPv4 destinations must be sent to such addresses.
+    /// A port of zero makes the OS pick an unused port.
+    pub fn bind_dual_stack(port: u16) -> Result<Self, io::Error> {
+        let sock = create_socket(AF_INET6, false)?;
+        set_int_option(sock.as_raw_fd(), IPPROTO_IPV6, IPV6_V6ONLY, 0)?;
+        bind_socket(&sock, &SocketAddr::from((Ipv6Addr::UNSPECIFIED, port)))?;
+        Ok(sock)
+    }
+
+    /// Create a new handle to the same socket, by duplicating the file descriptor.
+    ///
+    /// The handles share the socket and therefore all options, including
+    /// checksum coverage: changing an option through one of them also
+    /// changes it for the other.
+    pub fn try_clone(&self) -> Result<Self, io::Error> {
+        match self.as_udp.try_clone() {
+            Ok(clone) => Ok(UdpLiteSocket { as_udp: clone }),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Duplicate the file descriptor and then set the checksum coverage
+    /// of sent datagrams and the receive filter.
+    ///
+    /// **This also changes the coverage of the original**, because both
+    /// handles refer to the same socket; the coverage is not per file
+    /// descriptor. This method only saves some lines when a clone is
+    /// intended for reconfiguring the socket.
+    /// For two independently configured sockets, bind a second socket
+    /// (possibly to the same address with `SO_REUSEPORT`).
+    pub fn clone_with_coverage(&self,  send: Option<u16>,  recv_filter: Option<u16>)
+    -> Result<Self, io::Error> {
+        let clone = self.try_clone()?;
+        clone.set_send_checksum_coverage(send)?;
+        clone.set_recv_checksum_coverage_filter(recv_filter)?;
+        Ok(clone)
+    }
+
+    /// Duplicate the file descriptor with close-on-exec set atomically.
+    /// (`fcntl(F_DUPFD_CLOEXEC)`)
+    ///
+    /// Unlike setting close-on-exec after duplicating, this leaves no window
+    /// where another thread can `fork()` and `exec()` with the new
+    /// file descriptor inherited.
+    /// ([`try_clone()`](#method.try_clone) also duplicates like this,
+    /// but only as an implementation detail of `std`.)
+    pub fn try_clone_cloexec(&self) -> Result<Self, io::Error> {
+        match unsafe { fcntl(self.as_raw_fd(), F_DUPFD_CLOEXEC, 0) } {
+            -1 => Err(io::Error::last_os_error()),
+            fd => Ok(unsafe { UdpLiteSocket::from_raw_fd(fd) }),
+        }
+    }
+
+    /// Adopt a bound UDP-Lite socket passed to the process,
+    /// such as by systemd socket activation. (`LISTEN_FDS`)
+    ///
+    /// Unlike `from_raw_fd()`, this checks that the file descriptor is a
+    /// UDP-Lite socket and bound to a port.
+    /// Close-on-exec is then set, as the file descriptors are passed without
+    /// it, and it's set for sockets created by this crate.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `InvalidInput` if the file descriptor is not a UDP-Lite
+    /// socket, or is not bound. The file descriptor is not closed when this
+    /// fails.
+    ///
+    /// # Safety
+    ///
+    /// Like `from_raw_fd()`, the returned socket takes ownership of `fd`,
+    /// so it must be open and not owned by anything else.
+    pub unsafe fn from_activation_fd(fd: RawFd) -> Result<Self, io::Error> {
+        check_is_udplite(fd)?;
+        // don't close the file descriptor on errors
+        let socket = mem::ManuallyDrop::new(UdpLiteSocket::from_raw_fd(fd));
+        if !socket.is_bound() {
+            return Err(io::Error::new(InvalidInput, "Socket is not bound"));
+        }
+        socket.set_cloexec(true)?;
+        Ok(mem::ManuallyDrop::into_inner(socket))
+    }
+
+    /// Take ownership of a socket obtained elsewhere, after checking that it
+    /// is a UDP-Lite socket, and make it blocking or non-blocking.
+    ///
+    /// This is a safe alternative to `from_raw_fd()` which accepts anything
+    /// that converts into an `UdpSocket`, including an `OwnedFd` (on
+    /// Rust 1.63 and later) and `socket2::Socket`.
+    /// Unlike [`from_activation_fd()`](#method.from_activation_fd) the socket
+    /// doesn't need to be bound, and close-on-exec isn't changed.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `InvalidInput` if it is not a UDP-Lite socket.
+    /// The file descriptor is closed when this fails, like when any other
+    /// owned file descriptor is dropped.
+    pub fn adopt_fd<F: Into<UdpSocket>>(fd: F,  nonblocking: bool) -> Result<Self, io::Error> {
+        let socket = fd.into();
+        check_is_udplite(socket.as_raw_fd())?;
+        socket.set_nonblocking(nonblocking)?;
+        Ok(UdpLiteSocket { as_udp: socket })
+    }
+
+    /// Get whether the socket is IPv4 or IPv6.
+    ///
+    /// Unlike `local_addr()`, this also works for sockets that are not bound
+    /// yet, which can be obtained through `from_raw_fd()`.
+    pub fn domain(&self) -> Result<AddressFamily, io::Error> {
+        // safe because it doesn't store any fancy Rust types
+        let mut storage = unsafe { mem::zeroed::<sockaddr_storage>() };
+        let ret = unsafe {
+            let mut len = mem::size_of::<sockaddr_storage>() as socklen_t;
+            getsockname(
+                self.as_raw_fd(),
+                &mut storage as *mut sockaddr_storage as *mut sockaddr,
+                &mut len as *mut socklen_t,
+            )
+        };
+        match (ret, storage.ss_family as c_int) {
+            (-1, _) => Err(io::Error::last_os_error()),
+            (_, AF_INET) => Ok(AddressFamily::Ipv4),
+            (_, AF_INET6) => Ok(AddressFamily::Ipv6),
+            (_, _) => Err(io::Error::new(InvalidData, "Socket is neither IPv4 nor IPv6")),
+        }
+    }
+
+    // send_cscov(,) -> Result<u16>
+    // set_send_cscov(, u16) -> Result<()>
+    // send(, &[u8], SocketAddr) -
+    // send_vectored
+    // send_to()
+    // send_many
+    // 
     /// Change how many bytes of the payload of sent datagrams are covered by checksum.
     ///
     /// `None` means the entire datagram is covered, and this is the default
     /// for newly created sockets.
     ///
+    /// A coverage greater than the payload of a datagram is clamped to its
+    /// length, so that datagram is sent as entirely covered.
+    /// This includes datagrams without payload, where even `Some(0)` covers
+    /// the entire datagram, as the 8-byte header is always covered.
+    /// On Linux it then passes any
+    /// [receive filter](#method.set_recv_checksum_coverage_filter),
+    /// even filters higher than its length, but FreeBSD's exact-match filter
+    /// discards it if the filter is higher.
+    ///
     /// # Errors
     ///
     /// This will fail if the file descriptor for some reason is not a UDP-Lite
@@ -267,48 +1348,74 @@ impl UdpLiteSocket {
             Some(payload) => payload as c_int + 8,
             None => 0,
         };
-        let ret = unsafe {
-            setsockopt(
-                self.as_raw_fd(),
-                IPPROTO_UDPLITE,
-                UDPLITE_SEND_CSCOV,
-                &coverage as *const c_int as *const c_void,
-                mem::size_of::<c_int>() as socklen_t,
-            )
-        };
-        match ret {
-            -1 => Err(io::Error::last_os_error()),
-            _ => Ok(()),
-        }
+        set_int_option(self.as_raw_fd(), IPPROTO_UDPLITE, UDPLITE_SEND_CSCOV, coverage)
     }
 
     /// Get how many bytes of the payload of sent datagrams are covered by checksum.
     ///
     /// `None` means the entire datagram is covered, and this is the default
     /// for newly created sockets.
+    ///
+    /// This is the coverage in effect: if the option was set to a value
+    /// that only covers part of the 8-byte header (by something other than
+    /// this crate), `Some(0)` is returned, as the header is always covered.
     pub fn send_checksum_coverage(&self)
     -> Result<Option<u16>, io::Error> {
-        let mut coverage: c_int = -1;
-        let ret = unsafe {
-            let mut len = mem::size_of::<c_int>() as socklen_t;
-            getsockopt(
-                self.as_raw_fd(),
-                IPPROTO_UDPLITE,
-                UDPLITE_SEND_CSCOV,
-                &mut coverage as *mut c_int as *mut c_void,
-                &mut len as *mut socklen_t,
-            )
-        };
-        match (ret, coverage) {
-            (0, 0) => Ok(None),
-            (0, 8..=0xffff) => Ok(Some(coverage as u16 - 8)),
-            (0, 1..=7) => Err(io::Error::new(InvalidData, "Returned coverage only partially covers header")),
-            (0, _) => Err(io::Error::new(InvalidData, "Returned coverage is outside of valid range (for IPv6)")),
-            (-1, _) => Err(io::Error::last_os_error()),
-            (_, _) => Err(io::Error::new(InvalidData, "Unexpected return value from getsockopt()")),
+        match get_int_option(self.as_raw_fd(), IPPROTO_UDPLITE, UDPLITE_SEND_CSCOV)? {
+            0 => Ok(None),
+            coverage @ 8..=0xffff => Ok(Some(coverage as u16 - 8)),
+            // the header is always covered, so this is the effective coverage
+            1..=7 => Ok(Some(0)),
+            _ => Err(io::Error::new(InvalidData, "Returned coverage is outside of valid range (for IPv6)")),
         }
     }
 
+    /// Change the checksum coverage of sent datagrams, and return what it
+    /// was before.
+    ///
+    /// This is for temporarily changing the coverage and restoring it
+    /// afterwards. It reads and then sets the option, which is two system
+    /// calls, so another thread changing the coverage in between isn't
+    /// detected: this is only atomic for sockets used by one thread.
+    ///
+    /// # Errors
+    ///
+    /// If reading the current coverage fails, it's not changed.
+    pub fn swap_send_checksum_coverage(&self,  coverage: Option<u16>)
+    -> Result<Option<u16>, io::Error> {
+        let previous = self.send_checksum_coverage()?;
+        self.set_send_checksum_coverage(coverage)?;
+        Ok(previous)
+    }
+
+    /// Temporarily change the checksum coverage of sent datagrams, until the
+    /// returned guard is dropped.
+    ///
+    /// The guard restores the previous coverage when it goes out of scope,
+    /// including on early returns and panics.
+    /// Like [`swap_send_checksum_coverage()`](#method.swap_send_checksum_coverage)
+    /// this isn't thread-safe: the coverage applies to all datagrams sent
+    /// through the socket (and its clones) while the guard exists, and a
+    /// coverage set by another thread in the meantime is overwritten
+    /// when it's dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let socket = udplite::UdpLiteSocket::bind("127.0.0.1:0").expect("create socket");
+    /// {
+    ///     let _guard = socket.with_send_coverage(Some(8)).expect("change coverage");
+    ///     assert_eq!(socket.send_checksum_coverage().unwrap(), Some(8));
+    ///     // send datagrams with partial coverage here
+    /// }
+    /// assert_eq!(socket.send_checksum_coverage().unwrap(), None);
+    /// ```
+    pub fn with_send_coverage(&self,  coverage: Option<u16>)
+    -> Result<CoverageGuard<'_>, io::Error> {
+        let previous = self.swap_send_checksum_coverage(coverage)?;
+        Ok(CoverageGuard { socket: self,  previous })
+    }
+
     // send(, &[u8], SocketAddr) -
     // send_vectored
     // send_to()
@@ -319,55 +1426,413 @@ impl UdpLiteSocket {
     /// Received datagrams with lesser coverage will be discarded by the OS.
     /// FreeBSD additionally discards any packets with a checksum coverage
     /// higher than this filter, requiring them to match exactly.
-    //
-    // FIXME what does `None` mean here?
+    /// ([`recv_filter_is_exact_match()`](fn.recv_filter_is_exact_match.html)
+    /// can be used to check for this.)
+    ///
+    /// There is no way to get the checksum coverage of individual received
+    /// datagrams: Neither Linux nor FreeBSD provide it as ancillary data
+    /// (see [udplite(7)](http://man7.org/linux/man-pages/man7/udplite.7.html)
+    /// and [udplite(4)](https://www.freebsd.org/cgi/man.cgi?query=udplite)),
+    /// and reading `UDPLITE_RECV_CSCOV` only returns this filter.
+    /// What this filter guarantees is therefore all that can be known.
+    ///
+    /// # `None` and `Some(0)`
+    ///
+    /// `None` requires datagrams to be entirely covered by the checksum,
+    /// and discards all partially covered datagrams.
+    /// `Some(0)` accepts every datagram, as the 8-byte header is always
+    /// covered.
+    ///
+    /// Newly created sockets accept every datagram, like with `Some(0)`,
+    /// but (on Linux) [`recv_checksum_coverage_filter()`](#method.recv_checksum_coverage_filter)
+    /// returns `None` for them, because the OS doesn't differentiate between
+    /// the two when reading the option.
+    ///
+    /// Datagrams with checksum coverage greater than or equal to their length
+    /// count as entirely covered.
     pub fn set_recv_checksum_coverage_filter(&self,  coverage: Option<u16>)
     -> Result<(), io::Error> {
         let coverage: c_int = match coverage {
             Some(payload) => payload as c_int + 8,
             None => 0,
         };
-        let ret = unsafe {
-            setsockopt(
-                self.as_raw_fd(),
-                IPPROTO_UDPLITE,
-                UDPLITE_RECV_CSCOV,
-                &coverage as *const c_int as *const c_void,
-                mem::size_of::<c_int>() as socklen_t,
-            )
-        };
-        match ret {
-            -1 => Err(io::Error::last_os_error()),
-            _ => Ok(()),
-        }
+        set_int_option(self.as_raw_fd(), IPPROTO_UDPLITE, UDPLITE_RECV_CSCOV, coverage)
     }
 
-    /// Set the required checksum coverage of payloads of received datagrams.
+    /// Accept received datagrams regardless of their checksum coverage.
     ///
-    /// Received datagrams with lesser coverage will be discarded by the OS.
-    /// FreeBSD additionally discards any packets with a checksum coverage
-    /// higher than this filter, requiring them to match exactly.
-    //
-    // FIXME what does `None` mean here?
+    /// This is the most permissive receive filter, and the same as
+    /// `set_recv_checksum_coverage_filter(Some(0))`: the 8-byte header is
+    /// always covered, so every valid datagram has at least this coverage.
+    /// Unlike the filter of a newly created socket, which also accepts
+    /// everything, it reads back as `Some(0)`.
+    ///
+    /// On FreeBSD the filter must [match exactly](fn.recv_filter_is_exact_match.html),
+    /// so no filter accepts every coverage, and this only accepts datagrams
+    /// where just the header is covered.
+    pub fn accept_any_coverage(&self) -> Result<(), io::Error> {
+        self.set_recv_checksum_coverage_filter(Some(0))
+    }
+
+    /// Discard all received datagrams that are not entirely covered by
+    /// the checksum, like plain UDP.
+    ///
+    /// This is the same as `set_recv_checksum_coverage_filter(None)`.
+    /// Datagrams whose coverage is at least as long as the datagram count as
+    /// entirely covered.
+    pub fn require_full_coverage(&self) -> Result<(), io::Error> {
+        self.set_recv_checksum_coverage_filter(None)
+    }
+
+    /// Get the required checksum coverage of payloads of received datagrams.
+    ///
+    /// `None` means partially covered datagrams are discarded, except for
+    /// newly created sockets which accept all datagrams until the filter is
+    /// set. See [`set_recv_checksum_coverage_filter()`](#method.set_recv_checksum_coverage_filter)
+    /// for details.
+    ///
+    /// Like for [`send_checksum_coverage()`](#method.send_checksum_coverage),
+    /// a filter that only covers part of the header is returned as `Some(0)`.
     pub fn recv_checksum_coverage_filter(&self)
     -> Result<Option<u16>, io::Error> {
-        let mut coverage: c_int = -1;
-        let ret = unsafe {
-            let mut len = mem::size_of::<c_int>() as socklen_t;
+        match get_int_option(self.as_raw_fd(), IPPROTO_UDPLITE, UDPLITE_RECV_CSCOV)? {
+            0 => Ok(None),
+            coverage @ 8..=0xffff => Ok(Some(coverage as u16 - 8)),
+            1..=7 => Ok(Some(0)),
+            _ => Err(io::Error::new(InvalidData, "Returned coverage is outside of valid range")),
+        }
+    }
+
+    /// Set both the checksum coverage of sent datagrams and the required
+    /// coverage of received datagrams.
+    ///
+    /// This is the same as calling
+    /// [`set_send_checksum_coverage()`](#method.set_send_checksum_coverage)
+    /// and then [`set_recv_checksum_coverage_filter()`](#method.set_recv_checksum_coverage_filter),
+    /// and if the latter fails, the send coverage has already been changed.
+    pub fn set_checksum_coverage(&self,  send: Option<u16>,  recv_filter: Option<u16>)
+    -> Result<(), io::Error> {
+        self.set_send_checksum_coverage(send)?;
+        self.set_recv_checksum_coverage_filter(recv_filter)
+    }
+
+    /// Get both the checksum coverage of sent datagrams and the required
+    /// coverage of received datagrams, in that order.
+    pub fn checksum_coverage(&self) -> Result<(Option<u16>, Option<u16>), io::Error> {
+        Ok((self.send_checksum_coverage()?, self.recv_checksum_coverage_filter()?))
+    }
+
+    /// Check the receive checksum coverage filter for values that are almost
+    /// certainly a mistake, and would cause datagrams to be silently discarded.
+    ///
+    /// The crate can't know what coverage peers send with, so this compares
+    /// the filter against the address family of the socket and its own
+    /// send coverage, assuming peers are configured the same way.
+    /// Returns `None` if nothing looks suspicious, including when there's
+    /// no filter (`None`).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use udplite::{UdpLiteSocket, FilterWarning};
+    /// let socket = UdpLiteSocket::bind("127.0.0.1:0").expect("create socket");
+    /// socket.set_checksum_coverage(Some(4), Some(8)).expect("set coverage");
+    /// let warning = socket.filter_sanity_check().expect("get coverage");
+    /// assert_eq!(warning, Some(FilterWarning::MismatchesSendCoverage {
+    ///     filter: 8,
+    ///     send: Some(4),
+    /// }));
+    /// ```
+    pub fn filter_sanity_check(&self) -> Result<Option<FilterWarning>, io::Error> {
+        let (send, filter) = match self.checksum_coverage()? {
+            (send, Some(filter)) => (send, filter),
+            (_, None) => return Ok(None),
+        };
+        let max_payload = match self.domain()? {
+            AddressFamily::Ipv4 => 0xffff - 20 - 8,
+            AddressFamily::Ipv6 => 0xffff - 8,
+        };
+        if filter > max_payload {
+            return Ok(Some(FilterWarning::LongerThanAnyPayload { filter,  max_payload }));
+        }
+        let mismatch = match send {
+            _ if crate::recv_filter_is_exact_match() => send != Some(filter),
+            Some(send) => send < filter,
+            None => false,
+        };
+        if mismatch {
+            Ok(Some(FilterWarning::MismatchesSendCoverage { filter,  send }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Check whether the local network stack delivers datagrams with
+    /// partial checksum coverage.
+    ///
+    /// This binds a second socket to an ephemeral port on the IP of this
+    /// socket (or loopback if it's bound to an unspecified address), and
+    /// sends it a datagram from this socket where only the first 4 bytes of
+    /// payload are covered, followed by uncovered garbage.
+    /// The send coverage of this socket is changed for that datagram only,
+    /// as with [`with_send_coverage()`](#method.with_send_coverage), so
+    /// datagrams other threads send in the meantime are also partially
+    /// covered.
+    /// As the datagram is sent from this socket, calling this on an unbound
+    /// socket binds it to an ephemeral port.
+    /// Returns whether the datagram arrived within 100 milliseconds.
+    ///
+    /// This only tests the local stack: firewall rules, kernels without
+    /// UDP-Lite and similar. Routers and NATs on the path to a remote peer
+    /// might still drop UDP-Lite, and the only way to find out is to
+    /// exchange datagrams with it.
+    pub fn self_test_partial_coverage(&self) -> Result<bool, io::Error> {
+        const PAYLOAD: &[u8] = b"covr\xde\xad\xbe\xef uncovered garbage";
+        let ip = match self.local_addr() {
+            Ok(addr) if !addr.ip().is_unspecified() => addr.ip(),
+            _ => match self.domain()? {
+                AddressFamily::Ipv4 => IpAddr::V4(Ipv4Addr::LOCALHOST),
+                AddressFamily::Ipv6 => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            },
+        };
+        let receiver = UdpLiteSocket::bind_addr(SocketAddr::new(ip, 0))?;
+        // match exactly, as FreeBSD requires
+        receiver.set_recv_checksum_coverage_filter(Some(4))?;
+        let receiver_addr = receiver.local_addr()?;
+        {
+            let _guard = self.with_send_coverage(Some(4))?;
+            self.send_to_with_flags(PAYLOAD, 0, receiver_addr)?;
+        }
+        let mut buf = [0u8; 64];
+        let deadline = Instant::now() + Duration::from_millis(100);
+        while let Some(timeout) = deadline.checked_duration_since(Instant::now()) {
+            match receiver.recv_from_timeout(&mut buf, timeout)? {
+                Some((len, _)) if &buf[..len] == PAYLOAD => return Ok(true),
+                // some other socket sent to the ephemeral port
+                Some(_) => continue,
+                None => break,
+            }
+        }
+        Ok(false)
+    }
+
+    /// Check whether the socket is bound to a port.
+    ///
+    /// Sockets created by this crate always are, but sockets created with
+    /// `from_raw_fd()` might not be. Errors from `getsockname()` are treated
+    /// as not bound.
+    pub fn is_bound(&self) -> bool {
+        match self.local_addr() {
+            Ok(addr) => addr.port() != 0,
+            Err(_) => false,
+        }
+    }
+
+    /// Check whether the socket is connected to a peer address.
+    ///
+    /// This is whether `getpeername()` succeeds; it fails with `ENOTCONN`
+    /// until `connect()` has been called.
+    /// Note that for datagram sockets being connected only means that
+    /// there's a default destination and that datagrams from other
+    /// addresses are filtered out; it doesn't mean the peer exists.
+    pub fn is_connected(&self) -> bool {
+        self.peer_addr().is_ok()
+    }
+
+    /// Get a `Debug` representation that also includes the peer address and
+    /// the checksum coverage settings.
+    ///
+    /// The normal `Debug` impl mirrors `UdpSocket`'s, and only shows the
+    /// local address and file descriptor.
+    /// The settings are read when formatting; if that fails the error kind
+    /// is shown instead.
+    pub fn debug_verbose(&self) -> impl Debug + '_ {
+        VerboseDebug(self)
+    }
+
+    /// Dissolve the association with the connected peer, so that datagrams
+    /// from any address are received again.
+    ///
+    /// This calls `connect()` with an address of family `AF_UNSPEC`.
+    /// FreeBSD disconnects and then fails with `EAFNOSUPPORT` for that,
+    /// so that error is ignored there.
+    /// Disconnecting a socket that isn't connected is not an error.
+    ///
+    /// Linux also releases the port if it was picked by the OS (by binding
+    /// to port 0), so this then binds the socket to the same port again.
+    /// If another socket grabbed the port in between, that fails with
+    /// `AddrInUse`, and the socket is left unbound.
+    pub fn disconnect(&self) -> Result<(), io::Error> {
+        let port = self.local_addr()?.port();
+        // zero the rest of the address
+        let mut addr = unsafe { mem::zeroed::<sockaddr_storage>() };
+        addr.ss_family = AF_UNSPEC as sa_family_t;
+        let addr_ptr = &addr as *const sockaddr_storage as *const sockaddr;
+        let len = mem::size_of::<sockaddr_storage>() as socklen_t;
+        if unsafe { connect(self.as_raw_fd(), addr_ptr, len) } == -1 {
+            let error = io::Error::last_os_error();
+            if !cfg!(target_os="freebsd") || error.raw_os_error() != Some(EAFNOSUPPORT) {
+                return Err(error);
+            }
+        }
+        let unbound = self.local_addr()?;
+        if unbound.port() == 0 && port != 0 {
+            bind_socket(self, &SocketAddr::new(unbound.ip(), port))?;
+        }
+        Ok(())
+    }
+
+    /// Get the protocol of the socket. (`SO_PROTOCOL`)
+    ///
+    /// This is `IPPROTO_UDPLITE` (136) for sockets created by this crate,
+    /// and can be used to verify that a file descriptor passed to
+    /// `from_raw_fd()` is actually a UDP-Lite socket.
+    ///
+    /// If the OS doesn't support `SO_PROTOCOL`, UDP-Lite sockets are instead
+    /// recognized by supporting the checksum coverage options,
+    /// and other sockets produce the `ENOPROTOOPT` error.
+    pub fn protocol(&self) -> Result<c_int, io::Error> {
+        get_protocol(self.as_raw_fd())
+    }
+
+    /// Get the type of the socket. (`SO_TYPE`)
+    ///
+    /// This is `SOCK_DGRAM` for sockets created by this crate.
+    pub fn socket_type(&self) -> Result<c_int, io::Error> {
+        get_int_option(self.as_raw_fd(), SOL_SOCKET, SO_TYPE)
+    }
+
+    /// Get any socket option as raw bytes. (`getsockopt()`)
+    ///
+    /// This is an escape hatch for options this crate doesn't wrap.
+    /// The value is written to the start of `buf`, and the returned length
+    /// is how much of it the OS wrote. Values longer than `buf` are
+    /// truncated by the OS for most options, but some fail with `InvalidInput`.
+    /// The value is in native byte order and layout, as for C.
+    pub fn get_sockopt_raw(&self,  level: c_int,  name: c_int,  buf: &mut[u8])
+    -> Result<usize, io::Error> {
+        let mut len = 0;
+        sockopt_retry(|| unsafe {
+            // set inside because the OS writes to it
+            len = buf.len() as socklen_t;
             getsockopt(
                 self.as_raw_fd(),
-                IPPROTO_UDPLITE,
-                UDPLITE_RECV_CSCOV,
-                &mut coverage as *mut c_int as *mut c_void,
+                level,
+                name,
+                buf.as_mut_ptr() as *mut c_void,
                 &mut len as *mut socklen_t,
             )
-        };
-        match (ret, coverage) {
-            (0, 0) => Ok(None),
-            (0, 8..=0xffff) => Ok(Some(coverage as u16 - 8)),
-            (0, _) => Err(io::Error::new(InvalidData, "Returned coverage is outside of valid range")),
-            (-1, _) => Err(io::Error::last_os_error()),
-            (_, _) => Err(io::Error::new(InvalidData, "Unexpected return value from getsockopt()")),
+        })?;
+        Ok(len as usize)
+    }
+
+    /// Set any socket option from raw bytes. (`setsockopt()`)
+    ///
+    /// This is an escape hatch for options this crate doesn't wrap.
+    /// `buf` must contain the value in native byte order and layout,
+    /// as for C; for `int` options that is `value.to_ne_bytes()`.
+    pub fn set_sockopt_raw(&self,  level: c_int,  name: c_int,  buf: &[u8])
+    -> Result<(), io::Error> {
+        sockopt_retry(|| unsafe {
+            setsockopt(
+                self.as_raw_fd(),
+                level,
+                name,
+                buf.as_ptr() as *const c_void,
+                buf.len() as socklen_t,
+            )
+        })
+    }
+
+    /// Connect the socket and set the checksum coverage of datagrams sent
+    /// through it.
+    ///
+    /// This is a convenience for session-style clients that send every
+    /// datagram with the same coverage: it calls
+    /// [`set_send_checksum_coverage()`](#method.set_send_checksum_coverage)
+    /// and then `connect()`, and nothing more.
+    /// The coverage is set first so that it's also in effect for datagrams
+    /// sent by other threads as soon as the socket is connected.
+    ///
+    /// # Errors
+    ///
+    /// If connecting fails, the coverage has already been changed.
+    pub fn connect_with_coverage<A: ToSocketAddrs>(&self,  addr: A,  send_coverage: Option<u16>)
+    -> Result<(), io::Error> {
+        self.set_send_checksum_coverage(send_coverage)?;
+        self.as_udp.connect(addr)
+    }
+
+    /// Connect the socket and cover the same number of bytes in both
+    /// directions.
+    ///
+    /// This sets both the checksum coverage of sent datagrams and the
+    /// receive filter to `Some(coverage)`
+    /// (like [`set_send_checksum_coverage()`](#method.set_send_checksum_coverage)
+    /// and [`set_recv_checksum_coverage_filter()`](#method.set_recv_checksum_coverage_filter)),
+    /// and then connects, for protocols where both ends agree on covering
+    /// the first `coverage` bytes.
+    /// The peer must send with at least that coverage, or (on FreeBSD)
+    /// exactly that coverage, or its datagrams are silently dropped.
+    ///
+    /// # Errors
+    ///
+    /// If setting the send coverage or connecting fails, the options that
+    /// were set before it stay changed.
+    pub fn connect_symmetric_coverage<A: ToSocketAddrs>(&self,  addr: A,  coverage: u16)
+    -> Result<(), io::Error> {
+        self.set_recv_checksum_coverage_filter(Some(coverage))?;
+        self.connect_with_coverage(addr, Some(coverage))
+    }
+
+    /// Connect the socket and return the local address it then has.
+    ///
+    /// Unlike with TCP, datagram sockets are usually not bound explicitly
+    /// before connecting: connecting an unbound socket (such as one created
+    /// with [`new_unbound()`](#method.new_unbound)) binds it to an ephemeral
+    /// port and the IP of the interface the peer is reached through.
+    /// Connecting a socket bound to an unspecified IP similarly sets its IP.
+    /// This returns the resulting address.
+    ///
+    /// # Errors
+    ///
+    /// If getting the local address fails, the socket is still connected.
+    pub fn connect_and_local_addr<A: ToSocketAddrs>(&self,  addr: A)
+    -> Result<SocketAddr, io::Error> {
+        self.as_udp.connect(addr)?;
+        self.local_addr()
+    }
+
+    /// Connect the socket and check that the peer doesn't reject datagrams.
+    ///
+    /// Connecting a datagram socket sends nothing, so this then sends a
+    /// zero-length datagram as a probe, and waits for up to `timeout` for
+    /// the ICMP error that a host without a socket on the port responds with.
+    /// If the peer sends something within the timeout, this returns
+    /// immediately (without receiving it).
+    /// Otherwise it waits for the entire timeout, so it should be a bit
+    /// longer than the round-trip time, and not much more.
+    ///
+    /// The probe is received as an empty datagram by the peer if it exists,
+    /// so the protocol must tolerate those.
+    /// Not receiving an error doesn't prove that the peer exists:
+    /// the ICMP message might be filtered or lost.
+    ///
+    /// # Errors
+    ///
+    /// A rejected probe fails with `ConnectionRefused`, and the socket stays
+    /// connected.
+    pub fn connect_checked<A: ToSocketAddrs>(&self,  addr: A,  timeout: Duration)
+    -> Result<(), io::Error> {
+        // wait indefinitely if the deadline isn't representable
+        let deadline = Instant::now().checked_add(timeout);
+        self.as_udp.connect(addr)?;
+        self.as_udp.send(&[])?;
+        // errors are reported as POLLERR regardless of events
+        self.poll_until(POLLIN, deadline)?;
+        match self.as_udp.take_error()? {
+            Some(error) => Err(error),
+            None => Ok(()),
         }
     }
 
@@ -378,10 +1843,17 @@ impl UdpLiteSocket {
     ///
     /// It is set by default for sockets created by this crate,
     /// as is standard behavior in Rust.
+    ///
+    /// This uses `fcntl(F_SETFD)`, which is POSIX, unlike the `FIOCLEX` and
+    /// `FIONCLEX` `ioctl()`s.
     pub fn set_cloexec(&self,  close_on_exec: bool) -> Result<(), io::Error> {
         unsafe {
-            let op = if close_on_exec {FIOCLEX} else {FIONCLEX};
-            match ioctl(self.as_raw_fd(), op) {
+            let flags = match fcntl(self.as_raw_fd(), F_GETFD) {
+                -1 => return Err(io::Error::last_os_error()),
+                flags if close_on_exec => flags | FD_CLOEXEC,
+                flags => flags & !FD_CLOEXEC,
+            };
+            match fcntl(self.as_raw_fd(), F_SETFD, flags) {
                 -1 => Err(io::Error::last_os_error()),
                 _ => Ok(()),
             }
@@ -411,8 +1883,1726 @@ impl UdpLiteSocket {
             }
         }
     }
-}
 
+    /// Enable or disable non-blocking mode for the socket.
+    ///
+    /// This is the same as `UdpSocket::set_nonblocking()`, and only exists
+    /// for discoverability next to [`nonblocking()`](#method.nonblocking).
+    pub fn set_nonblocking(&self,  nonblocking: bool) -> Result<(), io::Error> {
+        self.as_udp.set_nonblocking(nonblocking)
+    }
+
+    /// Check whether the socket is in non-blocking mode.
+    ///
+    /// Sockets created by [`bind_nonblocking()`](#method.bind_nonblocking)
+    /// start out non-blocking, and ones created by [`bind()`](#method.bind)
+    /// start out blocking.
+    ///
+    /// # Errors
+    ///
+    /// Like [`is_cloexec()`](#method.is_cloexec) this can pretty much only
+    /// fail if the file descriptor doesn't exist.
+    pub fn nonblocking(&self) -> Result<bool, io::Error> {
+        unsafe {
+            match fcntl(self.as_raw_fd(), F_GETFL) {
+                -1 => Err(io::Error::last_os_error()),
+                flags => Ok(flags & O_NONBLOCK != 0),
+            }
+        }
+    }
+
+    /// Enable or disable bypassing the routing table when sending. (`SO_DONTROUTE`)
+    ///
+    /// When enabled, datagrams are only sent to hosts on directly connected
+    /// networks, and sending to other addresses fails.
+    pub fn set_dont_route(&self,  dont_route: bool) -> Result<(), io::Error> {
+        set_int_option(self.as_raw_fd(), SOL_SOCKET, SO_DONTROUTE, dont_route as c_int)
+    }
+
+    /// Check whether the routing table is bypassed when sending. (`SO_DONTROUTE`)
+    ///
+    /// This is disabled by default.
+    pub fn dont_route(&self) -> Result<bool, io::Error> {
+        get_int_option(self.as_raw_fd(), SOL_SOCKET, SO_DONTROUTE).map(|enabled| enabled != 0)
+    }
+
+    /// Set the minimum number of queued bytes for the socket to be
+    /// considered readable. (`SO_RCVLOWAT`)
+    ///
+    /// For datagram sockets this doesn't work like for streams, and
+    /// datagrams are never merged or split to satisfy it:
+    /// * Linux accepts the option but ignores it for UDP-Lite, so `poll()`
+    ///   and `recv()` return as soon as a single datagram is queued.
+    ///   Zero is stored as 1.
+    /// * FreeBSD compares it against the total size of all queued datagrams
+    ///   when reporting readiness to `poll()` / `select()` / `kqueue`,
+    ///   but a blocking `recv()` still returns the first datagram
+    ///   when one arrives. The value is capped to the receive buffer size.
+    ///
+    /// This means the option can't be relied on to batch receives;
+    /// [`recv_many()`](#method.recv_many) does that portably.
+    ///
+    /// # Errors
+    ///
+    /// Values above `i32::MAX` fail with `InvalidInput`.
+    pub fn set_recv_lowat(&self,  bytes: usize) -> Result<(), io::Error> {
+        if bytes > c_int::MAX as usize {
+            return Err(io::Error::new(InvalidInput, "Low-water mark is too big"));
+        }
+        set_int_option(self.as_raw_fd(), SOL_SOCKET, SO_RCVLOWAT, bytes as c_int)
+    }
+
+    /// Get the receive low-water mark. (`SO_RCVLOWAT`)
+    ///
+    /// It is 1 by default.
+    pub fn recv_lowat(&self) -> Result<usize, io::Error> {
+        get_int_option(self.as_raw_fd(), SOL_SOCKET, SO_RCVLOWAT).map(|bytes| bytes as usize)
+    }
+
+    /// Always fails, because lingering doesn't apply to datagram sockets.
+    ///
+    /// `SO_LINGER` makes closing a socket wait until queued data has been
+    /// delivered, which only makes sense for connection-oriented protocols;
+    /// for UDP-Lite the OS would accept the option but it would have no effect.
+    /// This method exists so that code which sets it on sockets generically
+    /// gets told instead of silently doing nothing.
+    ///
+    /// # Errors
+    ///
+    /// Always returns `InvalidInput`.
+    pub fn set_linger(&self,  _linger: Option<Duration>) -> Result<(), io::Error> {
+        Err(io::Error::new(InvalidInput, "SO_LINGER is not meaningful for datagram sockets"))
+    }
+
+    /// Set the priority of packets sent from this socket. (`SO_PRIORITY`)
+    ///
+    /// Linux uses it to select the queue of the outgoing network interface,
+    /// and the meaning of values depends on the queueing discipline used.
+    /// Setting a priority above 6 requires the `CAP_NET_ADMIN` capability,
+    /// and fails with `PermissionDenied` without it.
+    ///
+    /// This option is Linux-specific.
+    #[cfg(any(target_os="linux", target_os="android"))]
+    pub fn set_priority(&self,  priority: u32) -> Result<(), io::Error> {
+        set_int_option(self.as_raw_fd(), SOL_SOCKET, SO_PRIORITY, priority as c_int)
+    }
+
+    /// Get the priority of packets sent from this socket. (`SO_PRIORITY`)
+    ///
+    /// It is zero by default.
+    ///
+    /// This option is Linux-specific.
+    #[cfg(any(target_os="linux", target_os="android"))]
+    pub fn priority(&self) -> Result<u32, io::Error> {
+        get_int_option(self.as_raw_fd(), SOL_SOCKET, SO_PRIORITY).map(|priority| priority as u32)
+    }
+
+    /// Allow binding to IP addresses that are not (yet) assigned to any
+    /// network interface. (`IP_FREEBIND`)
+    ///
+    /// This must be set before binding, so create the socket with
+    /// [`new_unbound()`](#method.new_unbound).
+    /// It doesn't require any privileges.
+    /// The IPv4 option is used for IPv6 sockets too, as Linux shares it.
+    ///
+    /// This option is Linux-specific.
+    #[cfg(any(target_os="linux", target_os="android"))]
+    pub fn set_freebind(&self,  freebind: bool) -> Result<(), io::Error> {
+        set_int_option(self.as_raw_fd(), IPPROTO_IP, IP_FREEBIND, freebind as c_int)
+    }
+
+    /// Get whether binding to non-local IP addresses is allowed. (`IP_FREEBIND`)
+    ///
+    /// This option is Linux-specific.
+    #[cfg(any(target_os="linux", target_os="android"))]
+    pub fn freebind(&self) -> Result<bool, io::Error> {
+        get_int_option(self.as_raw_fd(), IPPROTO_IP, IP_FREEBIND).map(|freebind| freebind != 0)
+    }
+
+    /// Let multiple sockets bind to the same address and port, and spread
+    /// incoming datagrams between them. (`SO_REUSEPORT_LB`)
+    ///
+    /// Unlike `SO_REUSEPORT`, which on FreeBSD delivers unicast datagrams to
+    /// only one of the sockets, this balances datagrams between all of them
+    /// based on a hash of the source and destination, so that datagrams from
+    /// one peer keep going to the same socket.
+    /// Every socket must have it set before binding, so create them with
+    /// [`new_unbound()`](#method.new_unbound).
+    ///
+    /// This option is FreeBSD-specific.
+    #[cfg(target_os="freebsd")]
+    pub fn set_reuse_port_lb(&self,  reuse: bool) -> Result<(), io::Error> {
+        set_int_option(self.as_raw_fd(), SOL_SOCKET, SO_REUSEPORT_LB, reuse as c_int)
+    }
+
+    /// Get whether datagrams are load-balanced between sockets bound to the
+    /// same address and port. (`SO_REUSEPORT_LB`)
+    ///
+    /// This option is FreeBSD-specific.
+    #[cfg(target_os="freebsd")]
+    pub fn reuse_port_lb(&self) -> Result<bool, io::Error> {
+        get_int_option(self.as_raw_fd(), SOL_SOCKET, SO_REUSEPORT_LB).map(|reuse| reuse != 0)
+    }
+
+    /// Enable transparent proxying. (`IP_TRANSPARENT`)
+    ///
+    /// This lets the socket bind to and send from non-local addresses,
+    /// and receive datagrams redirected to it by a `TPROXY` firewall rule.
+    /// Like [`set_freebind()`](#method.set_freebind) it must be set before
+    /// binding to a non-local address, and applies to IPv6 sockets too.
+    /// It requires the `CAP_NET_ADMIN` or `CAP_NET_RAW` capability,
+    /// and fails with `PermissionDenied` without it.
+    ///
+    /// This option is Linux-specific.
+    #[cfg(any(target_os="linux", target_os="android"))]
+    pub fn set_transparent(&self,  transparent: bool) -> Result<(), io::Error> {
+        set_int_option(self.as_raw_fd(), IPPROTO_IP, IP_TRANSPARENT, transparent as c_int)
+    }
+
+    /// Get whether transparent proxying is enabled. (`IP_TRANSPARENT`)
+    ///
+    /// This option is Linux-specific.
+    #[cfg(any(target_os="linux", target_os="android"))]
+    pub fn transparent(&self) -> Result<bool, io::Error> {
+        get_int_option(self.as_raw_fd(), IPPROTO_IP, IP_TRANSPARENT)
+            .map(|transparent| transparent != 0)
+    }
+
+    /// Set how many microseconds to busy poll the network device
+    /// when receiving with no datagrams queued. (`SO_BUSY_POLL`)
+    ///
+    /// This reduces latency at the cost of CPU usage, and only applies to
+    /// devices whose drivers support it. Zero disables busy polling.
+    /// Increasing the value requires the `CAP_NET_ADMIN` capability,
+    /// and fails with `PermissionDenied` without it.
+    ///
+    /// This option is Linux-specific.
+    #[cfg(any(target_os="linux", target_os="android"))]
+    pub fn set_busy_poll(&self,  microseconds: u32) -> Result<(), io::Error> {
+        set_int_option(self.as_raw_fd(), SOL_SOCKET, SO_BUSY_POLL, microseconds as c_int)
+    }
+
+    /// Get how many microseconds the network device is busy polled
+    /// when receiving. (`SO_BUSY_POLL`)
+    ///
+    /// The default comes from the `net.core.busy_read` sysctl,
+    /// which is normally zero.
+    ///
+    /// This option is Linux-specific.
+    #[cfg(any(target_os="linux", target_os="android"))]
+    pub fn busy_poll(&self) -> Result<u32, io::Error> {
+        get_int_option(self.as_raw_fd(), SOL_SOCKET, SO_BUSY_POLL).map(|us| us as u32)
+    }
+
+    /// Set the time-to-live of IPv4 packets sent from this socket. (`IP_TTL`)
+    ///
+    /// This shadows `UdpSocket`s method of the same name and does the same thing;
+    /// it's here together with [`set_hop_limit()`](#method.set_hop_limit),
+    /// which is the IPv6 equivalent.
+    pub fn set_ttl(&self,  ttl: u32) -> Result<(), io::Error> {
+        set_int_option(self.as_raw_fd(), IPPROTO_IP, IP_TTL, ttl as c_int)
+    }
+
+    /// Get the time-to-live of IPv4 packets sent from this socket. (`IP_TTL`)
+    pub fn ttl(&self) -> Result<u32, io::Error> {
+        get_int_option(self.as_raw_fd(), IPPROTO_IP, IP_TTL).map(|ttl| ttl as u32)
+    }
+
+    /// Set the hop limit of unicast IPv6 packets sent from this socket.
+    /// (`IPV6_UNICAST_HOPS`)
+    ///
+    /// This is the IPv6 equivalent of [`set_ttl()`](#method.set_ttl),
+    /// and fails for IPv4 sockets. Use [`domain()`](#method.domain)
+    /// to find out which of them applies.
+    pub fn set_hop_limit(&self,  limit: u32) -> Result<(), io::Error> {
+        set_int_option(self.as_raw_fd(), IPPROTO_IPV6, IPV6_UNICAST_HOPS, limit as c_int)
+    }
+
+    /// Get the hop limit of unicast IPv6 packets sent from this socket.
+    /// (`IPV6_UNICAST_HOPS`)
+    pub fn hop_limit(&self) -> Result<u32, io::Error> {
+        get_int_option(self.as_raw_fd(), IPPROTO_IPV6, IPV6_UNICAST_HOPS)
+            .map(|limit| limit as u32)
+    }
+
+    /// Set the IPv6 flow label of datagrams sent to the connected peer.
+    ///
+    /// Linux only sends flow labels that the socket has leased from the
+    /// kernel, so this leases `label` exclusively for this socket
+    /// (`IPV6_FLOWLABEL_MGR`), enables sending flow labels
+    /// (`IPV6_FLOWINFO_SEND`), and then reconnects to the peer with the label
+    /// in the address, which is where the kernel picks it up from.
+    /// A label of zero reconnects without a flow label.
+    ///
+    /// Datagrams sent with `send_to()` use the flow label of the destination
+    /// address after this, which must then also be leased or zero.
+    ///
+    /// This option is Linux-specific.
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidInput` if `label` doesn't fit in 20 bits,
+    ///   or if the socket is IPv4.
+    /// * `NotConnected` if the socket isn't connected.
+    /// * `AlreadyExists` if another socket has leased the label.
+    ///   The socket stays connected with its previous flow label then.
+    /// * If `label` is in the upper half of the range while the
+    ///   `net.ipv6.flowlabel_state_ranges` sysctl is enabled (which is the
+    ///   default), as those labels are reserved for automatically generated ones.
+    /// * `PermissionDenied` if the process has leased many labels
+    ///   and doesn't have `CAP_NET_ADMIN`.
+    #[cfg(any(target_os="linux", target_os="android"))]
+    pub fn set_flow_label(&self,  label: u32) -> Result<(), io::Error> {
+        if label > 0xf_ffff {
+            return Err(io::Error::new(InvalidInput, "Flow labels are 20 bits"));
+        }
+        let peer = match self.peer_addr()? {
+            SocketAddr::V6(peer) => peer,
+            SocketAddr::V4(_) => {
+                return Err(io::Error::new(InvalidInput, "Flow labels are IPv6-only"));
+            }
+        };
+        let mut lease_error = None;
+        if label != 0 {
+            let request = FlowLabelReq {
+                dst: peer.ip().octets(),
+                label: label.to_be(),
+                action: IPV6_FL_A_GET,
+                share: IPV6_FL_S_EXCL,
+                // with IPV6_FL_F_EXCL, a label that is already leased fails
+                // with EEXIST, also if it's leased by this socket
+                flags: IPV6_FL_F_CREATE | IPV6_FL_F_EXCL,
+                expires: 0,
+                linger: 0,
+                _pad: 0,
+            };
+            let ret = unsafe {
+                setsockopt(
+                    self.as_raw_fd(),
+                    IPPROTO_IPV6,
+                    IPV6_FLOWLABEL_MGR,
+                    &request as *const FlowLabelReq as *const c_void,
+                    mem::size_of::<FlowLabelReq>() as socklen_t,
+                )
+            };
+            if ret == -1 {
+                let error = io::Error::last_os_error();
+                if error.raw_os_error() != Some(EEXIST) {
+                    return Err(error);
+                }
+                lease_error = Some(error);
+            }
+        }
+        set_int_option(self.as_raw_fd(), IPPROTO_IPV6, IPV6_FLOWINFO_SEND, 1)?;
+        let flowinfo = (peer.flowinfo() & !0xf_ffffu32.to_be()) | label.to_be();
+        let peer = SocketAddrV6::new(*peer.ip(), peer.port(), flowinfo, peer.scope_id());
+        match (self.as_udp.connect(peer), lease_error) {
+            // connecting only accepts labels leased by this socket,
+            // and fails before changing anything if it isn't
+            (Err(ref e), Some(lease_error)) if e.raw_os_error() == Some(EINVAL) => {
+                Err(lease_error)
+            }
+            (result, _) => result,
+        }
+    }
+
+    /// Get the IPv6 flow label of datagrams sent to the connected peer.
+    ///
+    /// This is zero unless set with [`set_flow_label()`](#method.set_flow_label).
+    ///
+    /// This option is Linux-specific.
+    #[cfg(any(target_os="linux", target_os="android"))]
+    pub fn flow_label(&self) -> Result<u32, io::Error> {
+        match self.peer_addr()? {
+            SocketAddr::V6(peer) => Ok(u32::from_be(peer.flowinfo()) & 0xf_ffff),
+            SocketAddr::V4(_) => Err(io::Error::new(InvalidInput, "Flow labels are IPv6-only")),
+        }
+    }
+
+    /// Join a multicast group on the network interface with the given name.
+    ///
+    /// For IPv4 groups the interface is identified by its first IPv4
+    /// address (found with `getifaddrs()`), and for IPv6 groups by its
+    /// index (found with `if_nametoindex()`).
+    /// Then `UdpSocket`s `join_multicast_v4()` or `join_multicast_v6()`
+    /// is called.
+    ///
+    /// # Errors
+    ///
+    /// * `NotFound` if there is no interface with that name.
+    /// * `AddrNotAvailable` if `group` is IPv4 and the interface has no
+    ///   IPv4 address.
+    /// * Whatever joining the group fails with.
+    pub fn join_multicast_by_ifname(&self,  group: IpAddr,  ifname: &str)
+    -> Result<(), io::Error> {
+        match group {
+            IpAddr::V4(group) => {
+                self.as_udp.join_multicast_v4(&group, &interface_ipv4(ifname)?)
+            }
+            IpAddr::V6(group) => {
+                self.as_udp.join_multicast_v6(&group, interface_index(ifname)?)
+            }
+        }
+    }
+
+    /// Get how many incoming datagrams the kernel has discarded for this socket.
+    /// (`SK_MEMINFO_DROPS` from `SO_MEMINFO`)
+    ///
+    /// This includes datagrams dropped because their checksum coverage was
+    /// lower than the [receive filter](#method.set_recv_checksum_coverage_filter),
+    /// which are otherwise invisible: the kernel doesn't report them through
+    /// the error queue or any other per-datagram mechanism.
+    /// It also counts datagrams dropped because the receive buffer was full,
+    /// so compare the counter before and after to tell whether coverage
+    /// filtering is discarding traffic when the buffer isn't under pressure.
+    ///
+    /// The counter starts at zero and wraps around on overflow.
+    ///
+    /// This option is Linux-specific, and requires Linux 4.12 or newer.
+    #[cfg(any(target_os="linux", target_os="android"))]
+    pub fn drop_count(&self) -> Result<u32, io::Error> {
+        let mut meminfo = [0u32; SK_MEMINFO_VARS];
+        let mut len = mem::size_of_val(&meminfo) as socklen_t;
+        let ret = unsafe {
+            getsockopt(
+                self.as_raw_fd(),
+                SOL_SOCKET,
+                SO_MEMINFO,
+                meminfo.as_mut_ptr() as *mut c_void,
+                &mut len as *mut socklen_t,
+            )
+        };
+        if ret == -1 {
+            Err(io::Error::last_os_error())
+        } else if (len as usize) < (SK_MEMINFO_DROPS+1)*mem::size_of::<u32>() {
+            Err(io::Error::new(InvalidData, "SO_MEMINFO returned too few values"))
+        } else {
+            Ok(meminfo[SK_MEMINFO_DROPS])
+        }
+    }
+
+    /// Attach the socket's drop counter to every received datagram.
+    /// (`SO_RXQ_OVFL`)
+    ///
+    /// When enabled, [`recv_msg()`](#method.recv_msg) reports it in
+    /// [`RecvInfo.dropped`](struct.RecvInfo.html#structfield.dropped).
+    /// It's the same counter as [`drop_count()`](#method.drop_count),
+    /// so it also counts datagrams discarded by the checksum coverage filter.
+    /// An increase between two datagrams means that datagrams in between
+    /// were lost.
+    ///
+    /// This option is Linux-specific.
+    #[cfg(any(target_os="linux", target_os="android"))]
+    pub fn set_recv_overflow_counter(&self,  enabled: bool) -> Result<(), io::Error> {
+        set_int_option(self.as_raw_fd(), SOL_SOCKET, SO_RXQ_OVFL, enabled as c_int)
+    }
+
+    /// Get whether the drop counter is attached to received datagrams.
+    /// (`SO_RXQ_OVFL`)
+    ///
+    /// This option is Linux-specific.
+    #[cfg(any(target_os="linux", target_os="android"))]
+    pub fn recv_overflow_counter(&self) -> Result<bool, io::Error> {
+        get_int_option(self.as_raw_fd(), SOL_SOCKET, SO_RXQ_OVFL).map(|enabled| enabled != 0)
+    }
+
+    /// Get the number of bytes waiting to be received. (`FIONREAD` / `SIOCINQ`)
+    ///
+    /// On Linux this is the payload length of the next datagram in the queue,
+    /// not the sum of all queued datagrams, while FreeBSD reports the total
+    /// for all datagrams. Either way it's zero if the queue is empty.
+    pub fn recv_queue_len(&self) -> Result<usize, io::Error> {
+        let mut len: c_int = 0;
+        match unsafe { ioctl(self.as_raw_fd(), FIONREAD, &mut len as *mut c_int) } {
+            -1 => Err(io::Error::last_os_error()),
+            _ => Ok(len as usize),
+        }
+    }
+
+    /// Get the number of bytes in the send queue that haven't been sent yet.
+    /// (`SIOCOUTQ` on Linux and `FIONWRITE` on FreeBSD)
+    ///
+    /// On Linux this includes the kernel's bookkeeping overhead for each
+    /// datagram, so it's larger than the sum of the queued payloads.
+    pub fn send_queue_len(&self) -> Result<usize, io::Error> {
+        #[cfg(any(target_os="linux", target_os="android"))]
+        let request = TIOCOUTQ;
+        #[cfg(target_os="freebsd")]
+        let request = FIONWRITE;
+        let mut len: c_int = 0;
+        match unsafe { ioctl(self.as_raw_fd(), request, &mut len as *mut c_int) } {
+            -1 => Err(io::Error::last_os_error()),
+            _ => Ok(len as usize),
+        }
+    }
+
+    /// Enable or disable receiving the TOS byte / traffic class of incoming
+    /// datagrams. (`IP_RECVTOS` or `IPV6_RECVTCLASS`)
+    ///
+    /// Which option is set depends on whether this is an IPv4 or IPv6 socket.
+    /// The value can then be read with
+    /// [`recv_from_with_tos()`](#method.recv_from_with_tos).
+    pub fn set_recv_tos(&self,  enabled: bool) -> Result<(), io::Error> {
+        self.set_ip_option(IP_RECVTOS, IPV6_RECVTCLASS, enabled as c_int)
+    }
+
+    /// Check whether receiving the TOS byte / traffic class of incoming
+    /// datagrams is enabled.
+    pub fn recv_tos(&self) -> Result<bool, io::Error> {
+        self.ip_option(IP_RECVTOS, IPV6_RECVTCLASS).map(|enabled| enabled != 0)
+    }
+
+    /// Enable or disable receiving the destination address and interface
+    /// of incoming datagrams.
+    /// (`IP_PKTINFO` (`IP_RECVDSTADDR` on FreeBSD) or `IPV6_RECVPKTINFO`)
+    ///
+    /// Like the other `set_recv_*()` methods, this enables the metadata
+    /// persistently, so that [`recv_msg()`](#method.recv_msg) can then be
+    /// called with `RecvOptions::new()` in a hot loop, which doesn't set any
+    /// options and only parses the metadata that arrives.
+    pub fn set_recv_pktinfo(&self,  enabled: bool) -> Result<(), io::Error> {
+        self.set_ip_option(IPV4_RECV_PKTINFO, IPV6_RECVPKTINFO, enabled as c_int)
+    }
+
+    /// Check whether receiving the destination address and interface of
+    /// incoming datagrams is enabled.
+    pub fn recv_pktinfo(&self) -> Result<bool, io::Error> {
+        self.ip_option(IPV4_RECV_PKTINFO, IPV6_RECVPKTINFO).map(|enabled| enabled != 0)
+    }
+
+    /// Enable or disable receiving the original destination of incoming
+    /// datagrams. (`IP_RECVORIGDSTADDR` or `IPV6_RECVORIGDSTADDR`)
+    ///
+    /// This is for transparent proxies, which receive datagrams redirected
+    /// by the firewall and need the address they were sent to, which
+    /// `local_addr()` doesn't give.
+    pub fn set_recv_orig_dst(&self,  enabled: bool) -> Result<(), io::Error> {
+        self.set_ip_option(IP_RECVORIGDSTADDR, IPV6_RECVORIGDSTADDR, enabled as c_int)
+    }
+
+    /// Check whether receiving the original destination of incoming
+    /// datagrams is enabled.
+    pub fn recv_orig_dst(&self) -> Result<bool, io::Error> {
+        self.ip_option(IP_RECVORIGDSTADDR, IPV6_RECVORIGDSTADDR).map(|enabled| enabled != 0)
+    }
+
+    /// Enable or disable receiving the TTL / hop limit of incoming datagrams.
+    /// (`IP_RECVTTL` or `IPV6_RECVHOPLIMIT`)
+    pub fn set_recv_ttl(&self,  enabled: bool) -> Result<(), io::Error> {
+        self.set_ip_option(IP_RECVTTL, IPV6_RECVHOPLIMIT, enabled as c_int)
+    }
+
+    /// Check whether receiving the TTL / hop limit of incoming datagrams
+    /// is enabled.
+    pub fn recv_ttl(&self) -> Result<bool, io::Error> {
+        self.ip_option(IP_RECVTTL, IPV6_RECVHOPLIMIT).map(|enabled| enabled != 0)
+    }
+
+    /// Enable or disable receiving when incoming datagrams arrived.
+    /// (`SO_TIMESTAMP`)
+    pub fn set_recv_timestamp(&self,  enabled: bool) -> Result<(), io::Error> {
+        set_int_option(self.as_raw_fd(), SOL_SOCKET, SO_TIMESTAMP, enabled as c_int)
+    }
+
+    /// Check whether receiving when incoming datagrams arrived is enabled.
+    pub fn recv_timestamp(&self) -> Result<bool, io::Error> {
+        get_int_option(self.as_raw_fd(), SOL_SOCKET, SO_TIMESTAMP).map(|enabled| enabled != 0)
+    }
+
+    /// Configure which timestamps the OS generates and reports for datagrams.
+    /// (`SO_TIMESTAMPING`)
+    ///
+    /// `flags` is a combination of the `SOF_TIMESTAMPING_*` constants from
+    /// `linux/net_tstamp.h` (also available from the libc crate),
+    /// and `0` disables timestamping.
+    /// For received datagrams, `SOF_TIMESTAMPING_RX_SOFTWARE |
+    /// SOF_TIMESTAMPING_SOFTWARE` reports the time the kernel received them,
+    /// and `SOF_TIMESTAMPING_RX_HARDWARE | SOF_TIMESTAMPING_RAW_HARDWARE`
+    /// the time the network card received them.
+    /// [`recv_msg()`](#method.recv_msg) returns them in
+    /// [`RecvInfo.timestamp`](struct.RecvInfo.html#structfield.timestamp) and
+    /// [`RecvInfo.hardware_timestamp`](struct.RecvInfo.html#structfield.hardware_timestamp).
+    ///
+    /// Hardware timestamps also need to be enabled for the network interface
+    /// (with the `SIOCSHWTSTAMP` ioctl, for example by `hwstamp_ctl` or
+    /// `ptp4l`), and many network cards, including loopback, don't support
+    /// them at all. This option doesn't fail then; the hardware timestamps
+    /// are simply `None`.
+    ///
+    /// This option is Linux-specific.
+    #[cfg(any(target_os="linux", target_os="android"))]
+    pub fn set_timestamping(&self,  flags: u32) -> Result<(), io::Error> {
+        set_int_option(self.as_raw_fd(), SOL_SOCKET, SO_TIMESTAMPING, flags as c_int)
+    }
+
+    /// Get which timestamps the OS generates and reports for datagrams.
+    /// (`SO_TIMESTAMPING`)
+    ///
+    /// This option is Linux-specific.
+    #[cfg(any(target_os="linux", target_os="android"))]
+    pub fn timestamping(&self) -> Result<u32, io::Error> {
+        get_int_option(self.as_raw_fd(), SOL_SOCKET, SO_TIMESTAMPING).map(|flags| flags as u32)
+    }
+
+    /// Receive the next entry in the error queue of the socket. (`MSG_ERRQUEUE`)
+    ///
+    /// Transmit timestamps enabled with
+    /// [`set_timestamping()`](#method.set_timestamping) are delivered here,
+    /// as `SOF_TIMESTAMPING_TX_SOFTWARE`, `SOF_TIMESTAMPING_TX_SCHED` or
+    /// `SOF_TIMESTAMPING_TX_HARDWARE` together with
+    /// `SOF_TIMESTAMPING_SOFTWARE` or `SOF_TIMESTAMPING_RAW_HARDWARE`.
+    /// Enabling `SOF_TIMESTAMPING_OPT_ID` numbers the sent datagrams so that
+    /// timestamps can be matched with sends, and `SOF_TIMESTAMPING_OPT_TSONLY`
+    /// avoids copying the sent packet back.
+    /// `buf` receives that copy, which includes the IP and UDP-Lite headers.
+    ///
+    /// Reading the error queue never blocks: `WouldBlock` is returned if it's
+    /// empty. A pending entry makes the socket readable for `poll()`
+    /// (as `POLLERR`).
+    ///
+    /// This method is Linux-specific.
+    #[cfg(any(target_os="linux", target_os="android"))]
+    pub fn recv_error(&self,  buf: &mut[u8]) -> Result<ErrorQueueEntry, io::Error> {
+        // safe because it doesn't store any fancy Rust types
+        let mut storage = unsafe { mem::zeroed::<sockaddr_storage>() };
+        let mut entry = ErrorQueueEntry {
+            len: 0,
+            dest: None,
+            errno: 0,
+            tx_id: None,
+            tx_type: None,
+            tx_timestamp: None,
+            tx_hardware_timestamp: None,
+        };
+        let (len, addr_len, _) = self.recvmsg_cmsgs(buf, MSG_ERRQUEUE, &mut storage, |cmsg| {
+            match (cmsg.cmsg_level, cmsg.cmsg_type) {
+                (IPPROTO_IP, IP_RECVERR) | (IPPROTO_IPV6, IPV6_RECVERR) => {
+                    if let Some(error) = cmsg_data::<SockExtendedErr>(cmsg) {
+                        if error.origin == SO_EE_ORIGIN_TIMESTAMPING {
+                            entry.tx_id = Some(error.data);
+                            entry.tx_type = Some(error.info);
+                        } else {
+                            entry.errno = error.errno as i32;
+                        }
+                    }
+                }
+                (SOL_SOCKET, SCM_TIMESTAMPING) => {
+                    if let Some([software, _, hardware]) = cmsg_data::<[timespec; 3]>(cmsg) {
+                        entry.tx_timestamp = timespec_to_system_time(software);
+                        entry.tx_hardware_timestamp = timespec_to_system_time(hardware);
+                    }
+                }
+                _ => {}
+            }
+        })?;
+        entry.len = len;
+        if addr_len != 0 {
+            entry.dest = sockaddr_to_rust_addr(&storage, addr_len).ok();
+        }
+        Ok(entry)
+    }
+
+    /// Filter which datagrams this socket receives with a classic BPF program.
+    /// (`SO_ATTACH_FILTER`)
+    ///
+    /// The program runs for every datagram before it's queued on the socket,
+    /// with the packet starting at the UDP-Lite header, and datagrams it
+    /// returns 0 for are dropped without waking up the process.
+    /// Attaching a filter replaces any previously attached filter.
+    ///
+    /// The program isn't checked here, but the OS rejects invalid programs
+    /// and programs with more than 4096 instructions with `EINVAL`.
+    ///
+    /// This option is Linux-specific.
+    #[cfg(any(target_os="linux", target_os="android"))]
+    pub fn attach_filter(&self,  prog: &[SockFilter]) -> Result<(), io::Error> {
+        if prog.len() > u16::MAX as usize {
+            return Err(io::Error::new(InvalidInput, "Too many BPF instructions"));
+        }
+        let fprog = SockFprog { len: prog.len() as u16, filter: prog.as_ptr() };
+        let ret = unsafe {
+            setsockopt(
+                self.as_raw_fd(),
+                SOL_SOCKET,
+                SO_ATTACH_FILTER,
+                &fprog as *const SockFprog as *const c_void,
+                mem::size_of::<SockFprog>() as socklen_t,
+            )
+        };
+        if ret == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Filter which datagrams this socket receives with an eBPF program.
+    /// (`SO_ATTACH_BPF`)
+    ///
+    /// `prog_fd` must refer to a program of type `BPF_PROG_TYPE_SOCKET_FILTER`
+    /// that has already been loaded with the `bpf()` system call,
+    /// for example by libbpf. The program works like a filter from
+    /// [`attach_filter()`](#method.attach_filter), and replaces any
+    /// previously attached filter.
+    /// The socket keeps a reference to the program, so `prog_fd` can be
+    /// closed afterwards. Remove it with
+    /// [`detach_filter()`](#method.detach_filter).
+    ///
+    /// This option is Linux-specific and requires Linux 3.19 or later.
+    /// (Loading the program without `CAP_SYS_ADMIN` requires Linux 4.4.)
+    #[cfg(any(target_os="linux", target_os="android"))]
+    pub fn attach_bpf(&self,  prog_fd: RawFd) -> Result<(), io::Error> {
+        set_int_option(self.as_raw_fd(), SOL_SOCKET, SO_ATTACH_BPF, prog_fd)
+    }
+
+    /// Remove the filter attached with
+    /// [`attach_filter()`](#method.attach_filter) or
+    /// [`attach_bpf()`](#method.attach_bpf). (`SO_DETACH_FILTER`)
+    ///
+    /// Fails with `ENOENT` if no filter is attached.
+    ///
+    /// This option is Linux-specific.
+    #[cfg(any(target_os="linux", target_os="android"))]
+    pub fn detach_filter(&self) -> Result<(), io::Error> {
+        set_int_option(self.as_raw_fd(), SOL_SOCKET, SO_DETACH_FILTER, 0)
+    }
+
+    /// Set an `int` option whose name depends on whether this is an IPv4 or
+    /// IPv6 socket.
+    fn set_ip_option(&self,  v4_name: c_int,  v6_name: c_int,  value: c_int)
+    -> Result<(), io::Error> {
+        match self.domain()? {
+            AddressFamily::Ipv4 => set_int_option(self.as_raw_fd(), IPPROTO_IP, v4_name, value),
+            AddressFamily::Ipv6 => set_int_option(self.as_raw_fd(), IPPROTO_IPV6, v6_name, value),
+        }
+    }
+
+    /// Get an `int` option whose name depends on whether this is an IPv4 or
+    /// IPv6 socket.
+    fn ip_option(&self,  v4_name: c_int,  v6_name: c_int) -> Result<c_int, io::Error> {
+        match self.domain()? {
+            AddressFamily::Ipv4 => get_int_option(self.as_raw_fd(), IPPROTO_IP, v4_name),
+            AddressFamily::Ipv6 => get_int_option(self.as_raw_fd(), IPPROTO_IPV6, v6_name),
+        }
+    }
+
+    /// Receive a datagram and its TOS byte (IPv4) or traffic class (IPv6).
+    ///
+    /// The TOS byte contains the DSCP in its upper six bits and ECN in the
+    /// lower two.
+    /// It is only available if enabled with [`set_recv_tos()`](#method.set_recv_tos),
+    /// and is `None` otherwise.
+    pub fn recv_from_with_tos(&self,  buf: &mut[u8])
+    -> Result<(usize, SocketAddr, Option<u8>), io::Error> {
+        let mut tos = None;
+        let (len, from, _) = self.recv_from_cmsgs(buf, 0, |cmsg| {
+            if let Some(received) = parse_tos_cmsg(cmsg) {
+                tos = Some(received);
+            }
+        })?;
+        Ok((len, from, tos))
+    }
+
+    /// Receive a datagram together with the metadata selected by `opts`.
+    ///
+    /// This enables the socket options needed for the selected metadata
+    /// before receiving, and they remain enabled afterwards.
+    /// Metadata that wasn't selected might therefore also be returned if
+    /// it was enabled by a previous call or by methods such as
+    /// [`set_recv_tos()`](#method.set_recv_tos).
+    /// If nothing is selected, no options are set, so after enabling the
+    /// wanted metadata once with those methods, `RecvOptions::new()` is the
+    /// cheapest way to receive it.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use udplite::{UdpLiteSocket, RecvOptions};
+    /// let socket = UdpLiteSocket::bind("0.0.0.0:2048").unwrap();
+    /// let mut buf = [0; 1500];
+    /// let info = socket.recv_msg(&mut buf, RecvOptions::new().ttl(true).pktinfo(true)).unwrap();
+    /// println!("{} bytes from {} to {:?} with TTL {:?}", info.len, info.src_addr, info.dest, info.ttl);
+    /// ```
+    pub fn recv_msg(&self,  buf: &mut[u8],  opts: RecvOptions) -> Result<RecvInfo, io::Error> {
+        self.recv_msg_with_flags(buf, opts, 0)
+    }
+
+    /// Read the next datagram and its metadata without removing it from
+    /// the queue. (`MSG_PEEK`)
+    ///
+    /// This works like [`recv_msg()`](#method.recv_msg), and the next
+    /// receive returns the same datagram, so a small `buf` can be used to
+    /// route datagrams based on their first bytes before one of several
+    /// handlers receives them.
+    /// The returned length is how much was copied into `buf`.
+    pub fn peek_msg(&self,  buf: &mut[u8],  opts: RecvOptions) -> Result<RecvInfo, io::Error> {
+        self.recv_msg_with_flags(buf, opts, MSG_PEEK)
+    }
+
+    fn recv_msg_with_flags(&self,  buf: &mut[u8],  opts: RecvOptions,  flags: c_int)
+    -> Result<RecvInfo, io::Error> {
+        self.enable_recv_options(opts)?;
+        let mut info = RecvInfo {
+            len: 0,
+            src_addr: SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+            ttl: None,
+            timestamp: None,
+            hardware_timestamp: None,
+            dest: None,
+            ifindex: None,
+            tos: None,
+            dropped: None,
+            orig_dst: None,
+        };
+        let (len, src_addr, _) = self.recv_from_cmsgs(buf, flags, |cmsg| {
+            parse_metadata_cmsg(cmsg, &mut info)
+        })?;
+        info.len = len;
+        info.src_addr = src_addr;
+        Ok(info)
+    }
+
+    fn enable_recv_options(&self,  opts: RecvOptions) -> Result<(), io::Error> {
+        let fd = self.as_raw_fd();
+        if opts.timestamp {
+            set_int_option(fd, SOL_SOCKET, SO_TIMESTAMP, 1)?;
+        }
+        if !(opts.ttl || opts.pktinfo || opts.tos || opts.orig_dst) {
+            // avoid the system call in domain()
+            return Ok(());
+        }
+        match self.domain()? {
+            AddressFamily::Ipv4 => {
+                if opts.ttl {
+                    set_int_option(fd, IPPROTO_IP, IP_RECVTTL, 1)?;
+                }
+                if opts.pktinfo {
+                    set_int_option(fd, IPPROTO_IP, IPV4_RECV_PKTINFO, 1)?;
+                }
+                if opts.tos {
+                    set_int_option(fd, IPPROTO_IP, IP_RECVTOS, 1)?;
+                }
+                if opts.orig_dst {
+                    set_int_option(fd, IPPROTO_IP, IP_RECVORIGDSTADDR, 1)?;
+                }
+            }
+            AddressFamily::Ipv6 => {
+                if opts.ttl {
+                    set_int_option(fd, IPPROTO_IPV6, IPV6_RECVHOPLIMIT, 1)?;
+                }
+                if opts.pktinfo {
+                    set_int_option(fd, IPPROTO_IPV6, IPV6_RECVPKTINFO, 1)?;
+                }
+                if opts.tos {
+                    set_int_option(fd, IPPROTO_IPV6, IPV6_RECVTCLASS, 1)?;
+                }
+                if opts.orig_dst {
+                    set_int_option(fd, IPPROTO_IPV6, IPV6_RECVORIGDSTADDR, 1)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Receive a datagram with `recvmsg()`, and pass each received control
+    /// message to `on_cmsg`.
+    ///
+    /// Returns the received length, source address and `msg_flags`.
+    fn recv_from_cmsgs<F: FnMut(&cmsghdr)>(&self,  buf: &mut[u8],  flags: c_int,  on_cmsg: F)
+    -> Result<(usize, SocketAddr, c_int), io::Error> {
+        // safe because it doesn't store any fancy Rust types
+        let mut storage = unsafe { mem::zeroed::<sockaddr_storage>() };
+        let (received, addr_len, msg_flags) = self.recvmsg_cmsgs(buf, flags, &mut storage, on_cmsg)?;
+        let addr = sockaddr_to_rust_addr(&storage, addr_len)?;
+        Ok((received, addr, msg_flags))
+    }
+
+    /// Call `recvmsg()` with the address stored in `storage`, and pass each
+    /// received control message to `on_cmsg`.
+    ///
+    /// Returns the received length, address length and `msg_flags`.
+    /// The address length is zero if the OS didn't provide an address.
+    fn recvmsg_cmsgs<F: FnMut(&cmsghdr)>(&self,  buf: &mut[u8],  flags: c_int,
+            storage: &mut sockaddr_storage,  mut on_cmsg: F,
+    ) -> Result<(usize, socklen_t, c_int), io::Error> {
+        let mut iov = iovec { iov_base: buf.as_mut_ptr() as *mut c_void,  iov_len: buf.len() };
+        // u64 for alignment
+        let mut control = [0u64; 32];
+        // zero padding fields on musl
+        let mut header = unsafe { mem::zeroed::<msghdr>() };
+        header.msg_name = storage as *mut sockaddr_storage as *mut c_void;
+        header.msg_namelen = mem::size_of::<sockaddr_storage>() as socklen_t;
+        header.msg_iov = &mut iov as *mut iovec;
+        header.msg_iovlen = 1;
+        header.msg_control = control.as_mut_ptr() as *mut c_void;
+        header.msg_controllen = mem::size_of_val(&control) as _;
+        let received = send_recv_retry(|| unsafe {
+            recvmsg(self.as_raw_fd(), &mut header as *mut msghdr, flags)
+        })?;
+        unsafe {
+            let mut cmsg = CMSG_FIRSTHDR(&header);
+            while !cmsg.is_null() {
+                on_cmsg(&*cmsg);
+                cmsg = CMSG_NXTHDR(&header, cmsg);
+            }
+        }
+        Ok((received, header.msg_namelen, header.msg_flags))
+    }
+
+    /// Send a datagram with `sendmsg()` and one control message
+    /// containing `data`.
+    fn send_to_with_cmsg<T: Copy>(&self,  buf: &[u8],  addr: SocketAddr,
+            level: c_int,  kind: c_int,  data: T,
+    ) -> Result<usize, io::Error> {
+        let (mut sockaddr, addr_len) = rust_addr_to_sockaddr(&addr);
+        let mut iov = iovec { iov_base: buf.as_ptr() as *mut c_void,  iov_len: buf.len() };
+        // u64 for alignment
+        let mut control = [0u64; 8];
+        let space = unsafe { CMSG_SPACE(mem::size_of::<T>() as _) } as usize;
+        assert!(space <= mem::size_of_val(&control), "control message is too big");
+        // zero padding fields on musl
+        let mut header = unsafe { mem::zeroed::<msghdr>() };
+        header.msg_name = &mut sockaddr as *mut SockAddrIn as *mut c_void;
+        header.msg_namelen = addr_len;
+        header.msg_iov = &mut iov as *mut iovec;
+        header.msg_iovlen = 1;
+        header.msg_control = control.as_mut_ptr() as *mut c_void;
+        header.msg_controllen = space as _;
+        unsafe {
+            let cmsg = CMSG_FIRSTHDR(&header);
+            (*cmsg).cmsg_level = level;
+            (*cmsg).cmsg_type = kind;
+            (*cmsg).cmsg_len = CMSG_LEN(mem::size_of::<T>() as _) as _;
+            ptr::write_unaligned(CMSG_DATA(cmsg) as *mut T, data);
+        }
+        send_recv_retry(|| unsafe {
+            sendmsg(self.as_raw_fd(), &header as *const msghdr, 0)
+        }).map_err(|e| self.explain_family_mismatch(&addr, e))
+    }
+
+    /// Send a reply to a datagram received with
+    /// [`recv_msg()`](#method.recv_msg), with the same TOS byte (IPv4)
+    /// or traffic class (IPv6) as the received datagram.
+    ///
+    /// This lets DiffServ-aware services mirror the DSCP marking of clients.
+    /// The TOS is only known if the datagram was received with
+    /// [`RecvOptions::tos()`](struct.RecvOptions.html#method.tos) enabled;
+    /// if `incoming.tos` is `None` the reply is sent with the socket's
+    /// default TOS.
+    /// The TOS is set for this datagram only, with an `IP_TOS` or
+    /// `IPV6_TCLASS` control message.
+    pub fn reply_to(&self,  incoming: &RecvInfo,  buf: &[u8]) -> Result<usize, io::Error> {
+        let addr = incoming.src_addr;
+        let tos = match incoming.tos {
+            Some(tos) => tos,
+            None => return self.send_to_with_flags(buf, 0, addr),
+        };
+        match addr {
+            SocketAddr::V6(v6) if v6.ip().segments()[..6] != [0, 0, 0, 0, 0, 0xffff] => {
+                self.send_to_with_cmsg(buf, addr, IPPROTO_IPV6, IPV6_TCLASS, tos as c_int)
+            }
+            // IPv4-mapped destinations are sent with IPv4
+            _ => self.send_to_with_cmsg(buf, addr, IPPROTO_IP, IP_TOS, tos),
+        }
+    }
+
+    /// Send a datagram with the don't-fragment bit set, without changing the
+    /// socket's path MTU discovery setting.
+    ///
+    /// This is for one-off path MTU probes: the datagram is sent with an
+    /// `IPV6_DONTFRAG` control message, so it fails with `EMSGSIZE` if it's
+    /// bigger than the known path MTU instead of being fragmented.
+    ///
+    /// # Errors
+    ///
+    /// There is no per-datagram equivalent for IPv4, so IPv4 and IPv4-mapped
+    /// destinations fail with `Unsupported` without sending anything.
+    /// For those, set `IP_MTU_DISCOVER` (Linux) or `IP_DONTFRAG` (FreeBSD)
+    /// on the socket instead.
+    pub fn send_to_dontfrag(&self,  buf: &[u8],  addr: SocketAddr) -> Result<usize, io::Error> {
+        match addr {
+            SocketAddr::V6(v6) if v6.ip().segments()[..6] != [0, 0, 0, 0, 0, 0xffff] => {
+                self.send_to_with_cmsg(buf, addr, IPPROTO_IPV6, IPV6_DONTFRAG, 1 as c_int)
+            }
+            _ => Err(io::Error::new(Unsupported, "Don't-fragment can only be set per datagram for IPv6")),
+        }
+    }
+
+    /// Receive a datagram into a new array.
+    ///
+    /// This uses `MSG_TRUNC`, so the returned length is the full length of
+    /// the datagram: if it's greater than `N` the datagram was truncated,
+    /// and only the first `N` bytes are in the array.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let socket = udplite::UdpLiteSocket::bind("[::]:2048").unwrap();
+    /// loop {
+    ///     let (datagram, len, from) = socket.recv_from_array::<512>().unwrap();
+    ///     if len <= datagram.len() {
+    ///         println!("received {:?} from {}", &datagram[..len], from);
+    ///     }
+    /// }
+    /// ```
+    pub fn recv_from_array<const N: usize>(&self)
+    -> Result<([u8; N], usize, SocketAddr), io::Error> {
+        let mut buf = [0u8; N];
+        let (len, from) = self.recv_with_flags(&mut buf, MSG_TRUNC)?;
+        Ok((buf, len, from))
+    }
+
+    /// Receive a datagram into a new `Vec` of at most `max_len` bytes.
+    ///
+    /// The `Vec` is shrunk to the length of the datagram.
+    ///
+    /// # Errors
+    ///
+    /// Unlike [`incoming()`](#method.incoming), which truncates, this fails
+    /// with `InvalidData` if the datagram was longer than `max_len`.
+    /// (This is detected with `MSG_TRUNC`.) The datagram is then discarded.
+    pub fn recv_vec(&self,  max_len: usize) -> Result<(Vec<u8>, SocketAddr), io::Error> {
+        let mut buf = vec![0; max_len];
+        let (len, from) = self.recv_with_flags(&mut buf, MSG_TRUNC)?;
+        if len > max_len {
+            return Err(io::Error::new(InvalidData, "Datagram is longer than max_len"));
+        }
+        buf.truncate(len);
+        buf.shrink_to_fit();
+        Ok((buf, from))
+    }
+
+    /// Receive a datagram and note when `recvfrom()` returned.
+    ///
+    /// The time is read with `Instant::now()` in userspace right after the
+    /// datagram was received, so it includes however long the datagram was
+    /// queued on the socket and any scheduling delay, and isn't when the
+    /// datagram arrived at the machine.
+    /// Use [`set_timestamping()`](#method.set_timestamping) or
+    /// [`RecvOptions::timestamp()`](struct.RecvOptions.html#method.timestamp)
+    /// for timestamps from the OS.
+    pub fn recv_from_timed(&self,  buf: &mut[u8])
+    -> Result<(usize, SocketAddr, Instant), io::Error> {
+        let (len, from) = self.recv_with_flags(buf, 0)?;
+        Ok((len, from, Instant::now()))
+    }
+
+    /// Receive a datagram if one is available, without blocking.
+    ///
+    /// This uses `MSG_DONTWAIT` instead of changing the socket into
+    /// non-blocking mode, so it doesn't affect other threads using the socket.
+    /// `Ok(None)` is returned if no datagram was available.
+    pub fn try_recv_from(&self,  buf: &mut[u8])
+    -> Result<Option<(usize, SocketAddr)>, io::Error> {
+        match self.recv_with_flags(buf, MSG_DONTWAIT) {
+            Ok(received) => Ok(Some(received)),
+            Err(ref e) if e.kind() == WouldBlock => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Receive a datagram, waiting at most `timeout` for one to arrive.
+    ///
+    /// This waits with `poll()` and then receives with `MSG_DONTWAIT`,
+    /// so unlike setting a read timeout it doesn't change the socket,
+    /// and it works the same for blocking and non-blocking sockets.
+    /// If another thread received the datagram first, it keeps waiting
+    /// for the remaining time.
+    /// `Ok(None)` is returned if no datagram arrived before the timeout.
+    pub fn recv_from_timeout(&self,  buf: &mut[u8],  timeout: Duration)
+    -> Result<Option<(usize, SocketAddr)>, io::Error> {
+        // wait indefinitely if the deadline isn't representable
+        let deadline = Instant::now().checked_add(timeout);
+        loop {
+            if !self.poll_until(POLLIN, deadline)? {
+                return Ok(None);
+            }
+            if let Some(received) = self.try_recv_from(buf)? {
+                return Ok(Some(received));
+            }
+        }
+    }
+
+    /// Wait until the socket is readable, for at most `timeout`,
+    /// and return whether it became readable.
+    ///
+    /// This is a thin wrapper around `poll()` for custom event loops that
+    /// don't use mio; `None` waits indefinitely.
+    /// Pending errors also count as readable, so that the following
+    /// receive can report them.
+    pub fn readable_timeout(&self,  timeout: Option<Duration>) -> Result<bool, io::Error> {
+        self.poll_until(POLLIN, timeout.and_then(|timeout| Instant::now().checked_add(timeout)))
+    }
+
+    /// Wait until the socket is writable, for at most `timeout`,
+    /// and return whether it became writable.
+    ///
+    /// Like [`readable_timeout()`](#method.readable_timeout) this uses
+    /// `poll()`, and `None` waits indefinitely.
+    /// A socket is writable when its send buffer has room.
+    pub fn writable_timeout(&self,  timeout: Option<Duration>) -> Result<bool, io::Error> {
+        self.poll_until(POLLOUT, timeout.and_then(|timeout| Instant::now().checked_add(timeout)))
+    }
+
+    /// Wait with `poll()` until the socket has one of `events`
+    /// or `deadline` is reached, and return whether it became ready.
+    /// Without a deadline this waits indefinitely.
+    ///
+    /// Errors and hangups count as ready, so that the following call
+    /// can report them.
+    fn poll_until(&self,  events: c_short,  deadline: Option<Instant>)
+    -> Result<bool, io::Error> {
+        loop {
+            let timeout = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    // round up to not return before the deadline
+                    let ms = (remaining.as_nanos() + 999_999) / 1_000_000;
+                    if ms > c_int::MAX as u128 {c_int::MAX} else {ms as c_int}
+                }
+                None => -1,
+            };
+            let mut fd = pollfd { fd: self.as_raw_fd(),  events,  revents: 0 };
+            match unsafe { poll(&mut fd, 1, timeout) } {
+                -1 => {
+                    let error = io::Error::last_os_error();
+                    if error.kind() != Interrupted {
+                        return Err(error);
+                    }
+                }
+                0 if deadline.map_or(false, |deadline| Instant::now() >= deadline) => {
+                    return Ok(false);
+                }
+                0 => {}
+                _ => return Ok(true),
+            }
+        }
+    }
+
+    /// Receive a datagram from the connected peer if one is available,
+    /// without blocking.
+    ///
+    /// Like [`try_recv_from()`](#method.try_recv_from) this uses
+    /// `MSG_DONTWAIT` and returns `Ok(None)` if no datagram was available.
+    pub fn try_recv(&self,  buf: &mut[u8]) -> Result<Option<usize>, io::Error> {
+        let received = send_recv_retry(|| unsafe {
+            recv(self.as_raw_fd(), buf.as_mut_ptr() as *mut c_void, buf.len(), MSG_DONTWAIT)
+        });
+        match received {
+            Ok(received) => Ok(Some(received)),
+            Err(ref e) if e.kind() == WouldBlock => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Receive a datagram with `recvfrom()`, passing `flags` through unchanged.
+    ///
+    /// This is an escape hatch for flags this crate doesn't have dedicated
+    /// methods for, such as `MSG_PEEK`, `MSG_TRUNC` or `MSG_WAITALL`.
+    /// The returned length is what `recvfrom()` returned, so with `MSG_TRUNC`
+    /// it can be greater than `buf.len()`.
+    pub fn recv_with_flags(&self,  buf: &mut[u8],  flags: c_int)
+    -> Result<(usize, SocketAddr), io::Error> {
+        // A [u8] is a valid [MaybeUninit<u8>], and recvfrom() only writes
+        // initialized bytes.
+        let buf = unsafe { &mut*(buf as *mut [u8] as *mut [MaybeUninit<u8>]) };
+        self.recv_uninit_with_flags(buf, flags)
+    }
+
+    /// Receive a datagram into a buffer that doesn't need to be initialized.
+    ///
+    /// This avoids zeroing the buffer first, which can matter for large
+    /// buffers on high-throughput receive paths.
+    ///
+    /// Only the first `len` bytes of `buf` are initialized afterwards,
+    /// where `len` is the returned length; the rest are left as they were.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::mem::MaybeUninit;
+    /// let socket = udplite::UdpLiteSocket::bind("[::]:2048").unwrap();
+    /// let mut buf = [MaybeUninit::<u8>::uninit(); 1500];
+    /// let (len, from) = socket.recv_from_uninit(&mut buf).unwrap();
+    /// // safe because recv_from_uninit() initialized the first len bytes
+    /// let datagram = unsafe { &*(&buf[..len] as *const [MaybeUninit<u8>] as *const [u8]) };
+    /// println!("received {:?} from {}", datagram, from);
+    /// ```
+    pub fn recv_from_uninit(&self,  buf: &mut[MaybeUninit<u8>])
+    -> Result<(usize, SocketAddr), io::Error> {
+        self.recv_uninit_with_flags(buf, 0)
+    }
+
+    /// Receive a datagram, and store the source address in `storage`
+    /// without converting it.
+    ///
+    /// Returns the length of the datagram and of the address.
+    /// This is for code that works with `sockaddr`s directly, for example
+    /// to compare the source of many datagrams in their raw form.
+    /// Use [`sockaddr_to_rust_addr()`](fn.sockaddr_to_rust_addr.html)
+    /// to convert the address afterwards.
+    pub fn recv_from_raw(&self,  buf: &mut[u8],  storage: &mut sockaddr_storage)
+    -> Result<(usize, socklen_t), io::Error> {
+        let mut addr_len = mem::size_of::<sockaddr_storage>() as socklen_t;
+        let received = send_recv_retry(|| unsafe {
+            recvfrom(
+                self.as_raw_fd(),
+                buf.as_mut_ptr() as *mut c_void,
+                buf.len(),
+                0,
+                storage as *mut sockaddr_storage as *mut sockaddr,
+                &mut addr_len as *mut socklen_t,
+            )
+        })?;
+        Ok((received, addr_len))
+    }
+
+    fn recv_uninit_with_flags(&self,  buf: &mut[MaybeUninit<u8>],  flags: c_int)
+    -> Result<(usize, SocketAddr), io::Error> {
+        // safe because it doesn't store any fancy Rust types
+        let mut storage = unsafe { mem::zeroed::<sockaddr_storage>() };
+        let mut addr_len = mem::size_of::<sockaddr_storage>() as socklen_t;
+        let received = send_recv_retry(|| unsafe {
+            recvfrom(
+                self.as_raw_fd(),
+                buf.as_mut_ptr() as *mut c_void,
+                buf.len(),
+                flags,
+                &mut storage as *mut sockaddr_storage as *mut sockaddr,
+                &mut addr_len as *mut socklen_t,
+            )
+        })?;
+        let addr = sockaddr_to_rust_addr(&storage, addr_len)?;
+        Ok((received, addr))
+    }
+
+    /// Replace an error from sending to `addr` with a clearer one if the
+    /// address is of the wrong IP version for the socket.
+    ///
+    /// This is only checked after the kernel rejects the address, because
+    /// IPv6 sockets can send to IPv4 addresses unless `IPV6_V6ONLY` is set,
+    /// and so that successful sends don't pay for an extra system call.
+    fn explain_family_mismatch(&self,  addr: &SocketAddr,  error: io::Error) -> io::Error {
+        match error.raw_os_error() {
+            Some(EAFNOSUPPORT) | Some(EINVAL) | Some(ENETUNREACH) => {}
+            _ => return error,
+        }
+        match (self.domain(), addr) {
+            (Ok(AddressFamily::Ipv4), SocketAddr::V6(_)) => io::Error::new(
+                InvalidInput,
+                "cannot send to IPv6 address on an IPv4 UDP-Lite socket"
+            ),
+            // ENETUNREACH is also the normal error for unreachable networks
+            (Ok(AddressFamily::Ipv6), SocketAddr::V4(_))
+            if error.raw_os_error() != Some(ENETUNREACH)
+            || get_int_option(self.as_raw_fd(), IPPROTO_IPV6, IPV6_V6ONLY).ok() == Some(1) => {
+                io::Error::new(
+                    InvalidInput,
+                    "cannot send to IPv4 address on an IPv6 UDP-Lite socket"
+                )
+            }
+            _ => error,
+        }
+    }
+
+    /// Send a datagram to the given address.
+    ///
+    /// This shadows `UdpSocket`s method of the same name and does the same
+    /// thing, except that sending to an address of the wrong IP version
+    /// fails with a descriptive `InvalidInput` error.
+    /// Like `UdpSocket`s method, only the first resolved address is used.
+    /// For connected sockets, `send()` (through `Deref`) is cheaper, as it
+    /// calls `send(2)` without converting any address.
+    pub fn send_to<A: ToSocketAddrs>(&self,  buf: &[u8],  addr: A) -> Result<usize, io::Error> {
+        match addr.to_socket_addrs()?.next() {
+            Some(addr) => self.send_to_with_flags(buf, 0, addr),
+            None => Err(io::Error::new(InvalidInput, "no addresses to send data to")),
+        }
+    }
+
+    /// Send a datagram with `sendto()`, passing `flags` through unchanged.
+    ///
+    /// This is an escape hatch for flags this crate doesn't have dedicated
+    /// methods for, such as `MSG_DONTWAIT`, `MSG_MORE` or `MSG_CONFIRM`.
+    pub fn send_to_with_flags(&self,  buf: &[u8],  flags: c_int,  addr: SocketAddr)
+    -> Result<usize, io::Error> {
+        let (sockaddr, len) = rust_addr_to_sockaddr(&addr);
+        send_recv_retry(|| unsafe {
+            sendto(
+                self.as_raw_fd(),
+                buf.as_ptr() as *const c_void,
+                buf.len(),
+                flags,
+                sockaddr.as_ptr(),
+                len,
+            )
+        }).map_err(|e| self.explain_family_mismatch(&addr, e))
+    }
+
+    /// Send a datagram to an IPv6 address on a specific network interface.
+    ///
+    /// This is required for link-local (`fe80::/10`) destinations, which are
+    /// ambiguous without a scope. `ifindex` is stored as the `sin6_scope_id`
+    /// of the destination address.
+    pub fn send_to_scoped(&self,  buf: &[u8],  addr: Ipv6Addr,  port: u16,  ifindex: u32)
+    -> Result<usize, io::Error> {
+        let addr = SocketAddrV6::new(addr, port, 0, ifindex);
+        self.send_to_with_flags(buf, 0, SocketAddr::V6(addr))
+    }
+
+    /// Send a handcrafted UDP-Lite packet with `coverage_field` as its
+    /// checksum coverage field, through a raw socket.
+    ///
+    /// The packet has the port of this socket as its source port, and its
+    /// checksum is computed over the pseudo-header and the first
+    /// `coverage_field` bytes of the packet (including the 8-byte header),
+    /// or all of it if `coverage_field` is zero.
+    /// This bypasses the coverage set with
+    /// [`set_send_checksum_coverage()`](#method.set_send_checksum_coverage),
+    /// so tests can send exactly the coverage they need, including invalid
+    /// values (1 to 7 or longer than the packet) that the OS must discard.
+    ///
+    /// This is only available with the `raw_coverage` feature.
+    ///
+    /// # Errors
+    ///
+    /// Creating the raw socket fails with `PermissionDenied` unless the
+    /// process is root or has `CAP_NET_RAW`.
+    /// Fails with `InvalidInput` if the payload is too long for a datagram.
+    #[cfg(feature="raw_coverage")]
+    pub fn send_raw_with_coverage_field(&self,  payload: &[u8],  coverage_field: u16,
+            addr: SocketAddr,
+    ) -> Result<usize, io::Error> {
+        let total_len = payload.len() + 8;
+        if total_len > 0xffff {
+            return Err(io::Error::new(InvalidInput, "Payload is too long for a datagram"));
+        }
+        // the source IP is needed for the checksum, so find out which one
+        // the OS would use, and make sure the raw socket uses the same
+        let local = self.local_addr()?;
+        let src_ip = if local.ip().is_unspecified() {
+            let probe = UdpSocket::bind(SocketAddr::new(local.ip(), 0))?;
+            probe.connect(addr)?;
+            probe.local_addr()?.ip()
+        } else {
+            local.ip()
+        };
+        let mut pseudo = Vec::with_capacity(40 + total_len);
+        let domain = match (src_ip, addr.ip()) {
+            (IpAddr::V4(src), IpAddr::V4(dst)) => {
+                pseudo.extend_from_slice(&src.octets());
+                pseudo.extend_from_slice(&dst.octets());
+                pseudo.extend_from_slice(&[0, IPPROTO_UDPLITE as u8]);
+                pseudo.extend_from_slice(&(total_len as u16).to_be_bytes());
+                AF_INET
+            }
+            (IpAddr::V6(src), IpAddr::V6(dst)) => {
+                pseudo.extend_from_slice(&src.octets());
+                pseudo.extend_from_slice(&dst.octets());
+                pseudo.extend_from_slice(&(total_len as u32).to_be_bytes());
+                pseudo.extend_from_slice(&[0, 0, 0, IPPROTO_UDPLITE as u8]);
+                AF_INET6
+            }
+            _ => return Err(io::Error::new(InvalidInput, "Address is of a different IP version")),
+        };
+        let header_start = pseudo.len();
+        pseudo.extend_from_slice(&local.port().to_be_bytes());
+        pseudo.extend_from_slice(&addr.port().to_be_bytes());
+        pseudo.extend_from_slice(&coverage_field.to_be_bytes());
+        pseudo.extend_from_slice(&[0, 0]);
+        pseudo.extend_from_slice(payload);
+        let covered = match coverage_field as usize {
+            0 => total_len,
+            field => field.min(total_len),
+        };
+        let checksum = match internet_checksum(&pseudo[..header_start+covered]) {
+            0 => 0xffff, // zero means no checksum, which UDP-Lite doesn't allow
+            checksum => checksum,
+        };
+        let packet = &mut pseudo[header_start..];
+        packet[6..8].copy_from_slice(&checksum.to_be_bytes());
+
+        let raw = unsafe { socket(domain, SOCK_RAW | SOCK_CLOEXEC, IPPROTO_UDPLITE) };
+        if raw == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        // let UdpSocket close it
+        let raw = unsafe { UdpSocket::from_raw_fd(raw) };
+        // the port of raw sockets must be zero or the protocol
+        let (src, src_len) = rust_addr_to_sockaddr(&SocketAddr::new(src_ip, 0));
+        if unsafe { bind(raw.as_raw_fd(), src.as_ptr(), src_len) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        let (dst, dst_len) = rust_addr_to_sockaddr(&SocketAddr::new(addr.ip(), 0));
+        send_recv_retry(|| unsafe {
+            sendto(
+                raw.as_raw_fd(),
+                packet.as_ptr() as *const c_void,
+                packet.len(),
+                0,
+                dst.as_ptr(),
+                dst_len,
+            )
+        })?;
+        Ok(payload.len())
+    }
+
+    /// Send an empty datagram, as a keepalive for NAT mappings or
+    /// firewall state.
+    ///
+    /// A datagram without payload consists of only the 8-byte header, which
+    /// is always covered by the checksum, so it counts as entirely covered
+    /// regardless of the send coverage:
+    /// it passes any receive filter on Linux, and on FreeBSD only filters
+    /// of `None` and `Some(0)`.
+    pub fn send_keepalive_to(&self,  addr: SocketAddr) -> Result<(), io::Error> {
+        self.send_to_with_flags(&[], 0, addr).map(|_| ())
+    }
+
+    /// Send a datagram to an IPv6 multicast group out through a specific
+    /// network interface.
+    ///
+    /// The interface is selected with an `IPV6_PKTINFO` control message for
+    /// this datagram only, so unlike setting `IPV6_MULTICAST_IF` it doesn't
+    /// affect other datagrams, and one socket can announce on several
+    /// interfaces.
+    /// The scope ID of `group` should be 0 or the same as `ifindex`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `InvalidInput` if `group` is not a multicast address.
+    pub fn send_multicast_v6(&self,  buf: &[u8],  group: SocketAddrV6,  ifindex: u32)
+    -> Result<usize, io::Error> {
+        if !group.ip().is_multicast() {
+            return Err(io::Error::new(InvalidInput, "Not a multicast address"));
+        }
+        // safe because it doesn't store any fancy Rust types
+        let mut pktinfo = unsafe { mem::zeroed::<in6_pktinfo>() };
+        pktinfo.ipi6_ifindex = ifindex as _;
+        self.send_to_with_cmsg(buf, SocketAddr::V6(group), IPPROTO_IPV6, IPV6_PKTINFO, pktinfo)
+    }
+
+    /// Receive multiple datagrams with one system call, one into each buffer.
+    ///
+    /// This blocks until at least one datagram is available (unless the
+    /// socket is non-blocking), and then receives as many as are queued,
+    /// up to `bufs.len()`. (`recvmmsg()` with `MSG_WAITFORONE`)
+    ///
+    /// Returns the length and source address of each received datagram,
+    /// in the order of `bufs`.
+    /// Datagrams longer than their buffer are truncated, but this uses
+    /// `MSG_TRUNC`, so the returned length is the full length of the datagram:
+    /// if it's greater than the length of the buffer, the datagram was truncated.
+    ///
+    /// More than [`max_batch_size()`](fn.max_batch_size.html) buffers fail
+    /// with `InvalidInput`.
+    pub fn recv_many(&self,  bufs: &mut[&mut[u8]])
+    -> Result<Vec<(usize, SocketAddr)>, io::Error> {
+        let mut iovecs = bufs.iter_mut()
+            .map(|buf| iovec { iov_base: buf.as_mut_ptr() as *mut c_void,  iov_len: buf.len() })
+            .collect::<Vec<iovec>>();
+        self.recvmmsg(iovecs.iter_mut().map(|iov| (iov as *mut iovec, 1)), MSG_WAITFORONE)
+    }
+
+    /// Receive multiple datagrams, waiting until at least `min` have arrived
+    /// or `timeout` has passed.
+    ///
+    /// This waits with `poll()` and receives whatever is queued with
+    /// `recvmmsg()` and `MSG_DONTWAIT`, repeating until at least `min`
+    /// datagrams have been received.
+    /// When it returns successfully it guarantees that either
+    ///
+    /// * at least `min` datagrams were received (and possibly more,
+    ///   up to `bufs.len()`, if they were already queued), or
+    /// * the timeout passed, in which case all datagrams received before it
+    ///   are returned, which might be none.
+    ///
+    /// `min` is clamped to between 1 and `bufs.len()`, and `None` waits
+    /// indefinitely. Like [`recv_from_timeout()`](#method.recv_from_timeout)
+    /// it works the same for blocking and non-blocking sockets.
+    /// Lengths and truncation are reported like for
+    /// [`recv_many()`](#method.recv_many).
+    ///
+    /// This doesn't use the timeout parameter of `recvmmsg()`, because Linux
+    /// only checks it after each received datagram, so that `recvmmsg()`
+    /// without `MSG_WAITFORONE` can block forever if fewer than `bufs.len()`
+    /// datagrams arrive.
+    ///
+    /// # Errors
+    ///
+    /// If receiving fails after some datagrams have been received, those are
+    /// returned and the next receive will likely report the error.
+    pub fn recv_many_at_least(&self,  bufs: &mut[&mut[u8]],  min: usize,
+            timeout: Option<Duration>,
+    ) -> Result<Vec<(usize, SocketAddr)>, io::Error> {
+        check_batch_size(bufs.len())?;
+        let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+        let min = min.max(1).min(bufs.len());
+        let mut received = Vec::with_capacity(bufs.len());
+        while received.len() < min {
+            if !self.poll_until(POLLIN, deadline)? {
+                break;
+            }
+            let mut iovecs = bufs[received.len()..].iter_mut()
+                .map(|buf| iovec { iov_base: buf.as_mut_ptr() as *mut c_void,  iov_len: buf.len() })
+                .collect::<Vec<iovec>>();
+            match self.recvmmsg(iovecs.iter_mut().map(|iov| (iov as *mut iovec, 1)), MSG_DONTWAIT) {
+                Ok(more) => received.extend(more),
+                // another thread got there first
+                Err(ref e) if e.kind() == WouldBlock => {}
+                Err(e) if received.is_empty() => return Err(e),
+                Err(_) => break,
+            }
+        }
+        Ok(received)
+    }
+
+    /// Receive multiple datagrams with one system call, scattering each of
+    /// them over a group of buffers.
+    ///
+    /// Each element of `bufs` is the buffers for one datagram, which are
+    /// filled in order. This makes it possible to receive for example a
+    /// fixed-size protocol header into a separate buffer from the payload.
+    ///
+    /// Otherwise this works like [`recv_many()`](#method.recv_many),
+    /// and the returned lengths are the full length of each datagram,
+    /// which is greater than the total length of its buffers if it was truncated.
+    pub fn recv_many_vectored(&self,  bufs: &mut[&mut[IoSliceMut<'_>]])
+    -> Result<Vec<(usize, SocketAddr)>, io::Error> {
+        // IoSliceMut is guaranteed to be ABI compatible with iovec on unix
+        self.recvmmsg(bufs.iter_mut().map(|group| {
+            (group.as_mut_ptr() as *mut iovec, group.len())
+        }), MSG_WAITFORONE)
+    }
+
+    fn recvmmsg<I>(&self,  msgs: I,  flags: c_int) -> Result<Vec<(usize, SocketAddr)>, io::Error>
+    where I: ExactSizeIterator<Item=(*mut iovec, usize)> {
+        if msgs.len() == 0 {
+            return Ok(Vec::new());
+        }
+        check_batch_size(msgs.len())?;
+        // safe because it doesn't store any fancy Rust types
+        let mut addrs = vec![unsafe { mem::zeroed::<sockaddr_storage>() }; msgs.len()];
+        let mut headers = addrs.iter_mut().zip(msgs).map(|(addr, (iov, iovlen))| {
+            recv_mmsghdr(addr, iov, iovlen)
+        }).collect::<Vec<mmsghdr>>();
+        let received = self.recvmmsg_into(&mut headers, flags)?;
+        headers[..received].iter().zip(&addrs).map(|(header, addr)| {
+            let from = sockaddr_to_rust_addr(addr, header.msg_hdr.msg_namelen)?;
+            Ok((header.msg_len as usize, from))
+        }).collect()
+    }
+
+    /// Call `recvmmsg()` with `flags` and `MSG_TRUNC`,
+    /// and return how many datagrams were received.
+    fn recvmmsg_into(&self,  headers: &mut[mmsghdr],  flags: c_int)
+    -> Result<usize, io::Error> {
+        send_recv_retry(|| unsafe {
+            recvmmsg(
+                self.as_raw_fd(),
+                headers.as_mut_ptr(),
+                headers.len() as _,
+                (flags | MSG_TRUNC) as _,
+                ptr::null_mut(),
+            ) as isize
+        })
+    }
+
+    /// Receive multiple datagrams with one system call into a reusable
+    /// [`BatchBuffer`](struct.BatchBuffer.html), and iterate over them.
+    ///
+    /// This works like [`recv_many()`](#method.recv_many), but doesn't
+    /// allocate once the buffer has been created, and returns slices of the
+    /// received datagrams together with their source address.
+    /// Datagrams longer than the `max_len` of the buffer are truncated,
+    /// and buffers for more than [`max_batch_size()`](fn.max_batch_size.html)
+    /// datagrams fail with `InvalidInput`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let socket = udplite::UdpLiteSocket::bind("[::]:2048").unwrap();
+    /// let mut batch = udplite::BatchBuffer::new(32, 1500);
+    /// loop {
+    ///     for (datagram, from) in socket.recv_batch(&mut batch).unwrap() {
+    ///         println!("received {:?} from {}", datagram, from);
+    ///     }
+    /// }
+    /// ```
+    pub fn recv_batch<'a>(&self,  scratch: &'a mut BatchBuffer)
+    -> Result<impl Iterator<Item=(&'a [u8], SocketAddr)> + 'a, io::Error> {
+        scratch.received.clear();
+        if scratch.addrs.is_empty() {
+            return Ok(scratch.iter());
+        }
+        check_batch_size(scratch.addrs.len())?;
+        let max_len = scratch.max_len;
+        let data = scratch.data.as_mut_ptr();
+        for (i, (iov, (header, addr))) in scratch.iovecs.iter_mut()
+            .zip(scratch.headers.iter_mut().zip(&mut scratch.addrs))
+            .enumerate() {
+            *iov = iovec { iov_base: unsafe { data.add(i*max_len) } as *mut c_void,  iov_len: max_len };
+            *header = recv_mmsghdr(addr, iov, 1);
+        }
+        let received = self.recvmmsg_into(&mut scratch.headers, MSG_WAITFORONE)?;
+        for (header, addr) in scratch.headers[..received].iter().zip(&scratch.addrs) {
+            let from = sockaddr_to_rust_addr(addr, header.msg_hdr.msg_namelen)?;
+            scratch.received.push((header.msg_len as usize, from));
+        }
+        Ok(scratch.iter())
+    }
+
+    /// Send multiple datagrams, possibly to different addresses,
+    /// with one system call.
+    ///
+    /// Returns how many of the datagrams were sent, which might be fewer than
+    /// `msgs.len()`.
+    ///
+    /// More than [`max_batch_size()`](fn.max_batch_size.html) datagrams
+    /// fail with `InvalidInput` without sending any.
+    ///
+    /// See [`send_all_to()`](#method.send_all_to) for a method that sends all
+    /// of them.
+    ///
+    /// # Errors
+    ///
+    /// The error tells which datagram couldn't be sent, and why.
+    /// When `sendmmsg()` fails after sending some datagrams the OS doesn't
+    /// report the error, so then this tries once more with the rest to
+    /// find out: It returns what that sent in addition if anything,
+    /// and otherwise the error for the first unsent datagram.
+    /// This means that only unsendable datagrams, or a full send buffer,
+    /// result in an error.
+    pub fn send_many(&self,  msgs: &[(SocketAddr, &[u8])]) -> Result<usize, BatchSendError> {
+        send_batch(msgs.len(), |skip| {
+            self.sendmmsg(msgs[skip..].iter().map(|&(ref addr, buf)| (addr, buf)))
+        })
+    }
+
+    /// Send the same datagram to multiple addresses with one system call.
+    ///
+    /// Returns how many of the addresses it was sent to, which might be
+    /// fewer than `addrs.len()`.
+    /// Errors, including for more addresses than
+    /// [`max_batch_size()`](fn.max_batch_size.html), are reported like for
+    /// [`send_many()`](#method.send_many).
+    pub fn send_to_many(&self,  buf: &[u8],  addrs: &[SocketAddr])
+    -> Result<usize, BatchSendError> {
+        send_batch(addrs.len(), |skip| {
+            self.sendmmsg(addrs[skip..].iter().map(|addr| (addr, buf)))
+        })
+    }
+
+    fn sendmmsg<'a, I>(&self,  msgs: I) -> Result<usize, io::Error>
+    where I: ExactSizeIterator<Item=(&'a SocketAddr, &'a [u8])> {
+        if msgs.len() == 0 {
+            return Ok(0);
+        }
+        check_batch_size(msgs.len())?;
+        let mut addrs = Vec::with_capacity(msgs.len());
+        let mut iovecs = Vec::with_capacity(msgs.len());
+        let mut first_addr = None;
+        for (addr, buf) in msgs {
+            first_addr = first_addr.or(Some(addr));
+            addrs.push(rust_addr_to_sockaddr(addr));
+            iovecs.push(iovec { iov_base: buf.as_ptr() as *mut c_void,  iov_len: buf.len() });
+        }
+        let mut headers = addrs.iter_mut().zip(&mut iovecs).map(|((addr, len), iov)| {
+            // zero padding fields on musl
+            let mut header = unsafe { mem::zeroed::<mmsghdr>() };
+            header.msg_hdr.msg_name = addr as *mut SockAddrIn as *mut c_void;
+            header.msg_hdr.msg_namelen = *len;
+            header.msg_hdr.msg_iov = iov as *mut iovec;
+            header.msg_hdr.msg_iovlen = 1;
+            header
+        }).collect::<Vec<mmsghdr>>();
+        send_recv_retry(|| unsafe {
+            sendmmsg(self.as_raw_fd(), headers.as_mut_ptr(), headers.len() as _, 0) as isize
+        }).map_err(|e| match first_addr {
+            // an error is only returned if the first datagram wasn't sent
+            Some(addr) => self.explain_family_mismatch(addr, e),
+            None => e,
+        })
+    }
+
+    /// Send multiple datagrams, possibly to different addresses,
+    /// retrying until all have been sent.
+    ///
+    /// This calls [`send_many()`](#method.send_many) with the datagrams that
+    /// haven't been sent yet until all are sent or an error occurs.
+    /// Unlike `send_many()` it accepts any number of datagrams, and passes
+    /// at most [`max_batch_size()`](fn.max_batch_size.html) of them at a time.
+    ///
+    /// # Errors
+    ///
+    /// When an error is returned, some of the datagrams might have been sent.
+    ///
+    /// `WouldBlock` is returned if the socket is non-blocking and the send
+    /// buffer is full, or if the socket is blocking and the send timeout
+    /// expires.
+    pub fn send_all_to(&self,  msgs: &[(SocketAddr, &[u8])]) -> Result<(), io::Error> {
+        let mut remaining = msgs;
+        while !remaining.is_empty() {
+            let chunk = &remaining[..remaining.len().min(MAX_BATCH_SIZE)];
+            let sent = self.send_many(chunk).map_err(|e| e.error)?;
+            remaining = &remaining[sent..];
+        }
+        Ok(())
+    }
+
+    /// Get an iterator that receives datagrams into owned buffers.
+    ///
+    /// Each item is the result of a `recv_from()`, and the iterator never ends.
+    /// Datagrams longer than the max length (by default the maximum possible
+    /// payload of a datagram) are truncated.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let socket = udplite::UdpLiteSocket::bind("[::]:2048").unwrap();
+    /// for result in socket.incoming().max_len(1500) {
+    ///     let (datagram, from) = result.unwrap();
+    ///     println!("received {} bytes from {}", datagram.len(), from);
+    /// }
+    /// ```
+    pub fn incoming(&self) -> Incoming<'_> {
+        Incoming { socket: self,  buf: vec![0; 0xffff] }
+    }
+}
+
+
+
+/// `struct in6_flowlabel_req` from `linux/in6.h`, for `IPV6_FLOWLABEL_MGR`.
+#[cfg(any(target_os="linux", target_os="android"))]
+#[repr(C)]
+struct FlowLabelReq {
+    dst: [u8; 16],
+    label: u32,
+    action: u8,
+    share: u8,
+    flags: u16,
+    expires: u16,
+    linger: u16,
+    _pad: u32,
+}
+
+/// Checks that the socket is a UDP-Lite socket.
+///
+/// The socket is closed if it is not.
+#[cfg(feature="socket2")]
+impl TryFrom<socket2::Socket> for UdpLiteSocket {
+    type Error = io::Error;
+    fn try_from(socket: socket2::Socket) -> Result<Self, io::Error> {
+        check_is_udplite(socket.as_raw_fd())?;
+        Ok(unsafe { UdpLiteSocket::from_raw_fd(socket.into_raw_fd()) })
+    }
+}
+
+#[cfg(feature="socket2")]
+impl From<UdpLiteSocket> for socket2::Socket {
+    fn from(socket: UdpLiteSocket) -> Self {
+        unsafe { socket2::Socket::from_raw_fd(socket.into_raw_fd()) }
+    }
+}
 
 
 #[cfg(feature="mio_06")]
@@ -445,8 +3635,23 @@ impl Source for UdpLiteSocket {
     }
 }
 
+/// Makes it possible to register shared sockets.
+///
+/// `Source` can't be implemented for `Arc<UdpLiteSocket>` outside of mio,
+/// so register an `Arc` through a reference instead:
+///
+/// ```
+/// # extern crate mio_07 as mio;
+/// use std::sync::Arc;
+/// use mio::{Interest, Poll, Token};
+/// use udplite::UdpLiteSocket;
+///
+/// let socket = Arc::new(UdpLiteSocket::bind_nonblocking("127.0.0.1:0").unwrap());
+/// let poll = Poll::new().unwrap();
+/// poll.registry().register(&mut &*socket, Token(0), Interest::READABLE).unwrap();
+/// ```
 #[cfg(feature="mio_07")]
-impl<'a> Source for &'a UdpLiteSocket {
+impl Source for &UdpLiteSocket {
     fn register(&mut self,  registry: &Registry,  token: Token_07,  interest: Interest)
     -> Result<(), io::Error> {
         SourceFd(&self.as_raw_fd()).register(registry, token, interest)
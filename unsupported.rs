@@ -0,0 +1,564 @@
+/* Copyright 2020 Torbjørn Birch Moltu
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+
+//! Stand-ins for operating systems that don't support UDP-Lite,
+//! so that code using this crate compiles everywhere.
+//!
+//! `UdpLiteSocket` cannot be created, so all its methods are unreachable.
+
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, IntoRawFd, RawFd};
+use std::os::raw::c_int;
+use std::net::{UdpSocket, SocketAddr, SocketAddrV6, IpAddr, Ipv6Addr, ToSocketAddrs};
+use std::{fmt, io};
+use std::io::{IoSliceMut, Read, Write};
+use std::mem::MaybeUninit;
+use std::ops::Deref;
+use std::time::{Duration, Instant};
+use std::fmt::{Debug, Display};
+use crate::{AddressFamily, Incoming, RecvOptions, RecvInfo, BatchSendError, FilterWarning};
+use crate::CoverageGuard;
+
+#[derive(Clone, Copy)]
+enum Never {}
+
+fn unsupported() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "UDP-Lite is not supported by this OS")
+}
+
+/// Always returns false, as this OS doesn't support UDP-Lite.
+pub fn is_supported() -> bool {
+    false
+}
+
+/// The maximum number of datagrams the batch methods accept,
+/// which is the same as on Linux.
+pub fn max_batch_size() -> usize {
+    1024
+}
+
+/// Reusable buffers for receiving multiple datagrams, which can be created
+/// but not received into on this OS.
+#[derive(Debug)]
+pub struct BatchBuffer {
+    datagrams: usize,
+    max_len: usize,
+}
+
+impl BatchBuffer {
+    pub fn new(datagrams: usize,  max_len: usize) -> Self {
+        BatchBuffer { datagrams, max_len }
+    }
+
+    pub fn datagrams(&self) -> usize {
+        self.datagrams
+    }
+
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+}
+
+/// A UDP-Lite socket, which cannot be created on this OS.
+pub struct UdpLiteSocket {
+    never: Never,
+}
+
+impl Debug for UdpLiteSocket {
+    fn fmt(&self,  _: &mut fmt::Formatter) -> fmt::Result {
+        match self.never {}
+    }
+}
+
+impl Display for UdpLiteSocket {
+    fn fmt(&self,  _: &mut fmt::Formatter) -> fmt::Result {
+        match self.never {}
+    }
+}
+
+#[cfg(unix)]
+impl AsRawFd for UdpLiteSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        match self.never {}
+    }
+}
+#[cfg(unix)]
+impl IntoRawFd for UdpLiteSocket {
+    fn into_raw_fd(self) -> RawFd {
+        match self.never {}
+    }
+}
+
+impl Read for &UdpLiteSocket {
+    fn read(&mut self,  _: &mut[u8]) -> Result<usize, io::Error> {
+        match self.never {}
+    }
+}
+impl Read for UdpLiteSocket {
+    fn read(&mut self,  _: &mut[u8]) -> Result<usize, io::Error> {
+        match self.never {}
+    }
+}
+impl Write for &UdpLiteSocket {
+    fn write(&mut self,  _: &[u8]) -> Result<usize, io::Error> {
+        match self.never {}
+    }
+    fn flush(&mut self) -> Result<(), io::Error> {
+        match self.never {}
+    }
+}
+impl Write for UdpLiteSocket {
+    fn write(&mut self,  _: &[u8]) -> Result<usize, io::Error> {
+        match self.never {}
+    }
+    fn flush(&mut self) -> Result<(), io::Error> {
+        match self.never {}
+    }
+}
+
+impl Deref for UdpLiteSocket {
+    type Target = UdpSocket;
+    fn deref(&self) -> &UdpSocket {
+        match self.never {}
+    }
+}
+
+impl UdpLiteSocket {
+    /// The lowest possible checksum coverage, which covers only the 8-byte
+    /// UDP-Lite header.
+    pub const MIN_COVERAGE: u16 = 0;
+
+    /// Always fails with `ErrorKind::Unsupported`.
+    pub fn bind<A: ToSocketAddrs>(_: A) -> Result<Self, io::Error> {
+        Err(unsupported())
+    }
+
+    /// Always fails with `ErrorKind::Unsupported`.
+    pub fn bind_addr(_: SocketAddr) -> Result<Self, io::Error> {
+        Err(unsupported())
+    }
+
+    /// Always fails with `ErrorKind::Unsupported`.
+    pub fn bind_prefer<A: ToSocketAddrs>(_: A,  _: Option<AddressFamily>)
+    -> Result<Self, io::Error> {
+        Err(unsupported())
+    }
+
+    pub fn bind_v4<A: ToSocketAddrs>(_: A) -> Result<Self, io::Error> {
+        Err(unsupported())
+    }
+
+    pub fn bind_v6<A: ToSocketAddrs>(_: A) -> Result<Self, io::Error> {
+        Err(unsupported())
+    }
+
+    /// Always fails with `ErrorKind::Unsupported`.
+    pub fn bind_nonblocking<A: ToSocketAddrs>(_: A) -> Result<Self, io::Error> {
+        Err(unsupported())
+    }
+
+    /// Always fails with `ErrorKind::Unsupported`.
+    pub fn bind_dual_stack(_: u16) -> Result<Self, io::Error> {
+        Err(unsupported())
+    }
+
+    /// Always fails with `ErrorKind::Unsupported`.
+    #[cfg(feature="serde")]
+    pub fn from_config(_: &crate::UdpLiteConfig) -> Result<Self, io::Error> {
+        Err(unsupported())
+    }
+
+    /// Always fails with `ErrorKind::Unsupported`.
+    pub fn pair() -> Result<(Self, Self), io::Error> {
+        Err(unsupported())
+    }
+
+    /// Always fails with `ErrorKind::Unsupported`.
+    pub fn new_unbound(_: AddressFamily) -> Result<Self, io::Error> {
+        Err(unsupported())
+    }
+
+    pub fn bind_to(&self,  _: SocketAddr) -> Result<(), io::Error> {
+        match self.never {}
+    }
+
+    pub fn try_clone(&self) -> Result<Self, io::Error> {
+        match self.never {}
+    }
+
+    pub fn clone_with_coverage(&self,  _: Option<u16>,  _: Option<u16>)
+    -> Result<Self, io::Error> {
+        match self.never {}
+    }
+
+    pub fn try_clone_cloexec(&self) -> Result<Self, io::Error> {
+        match self.never {}
+    }
+
+    /// Always fails with `ErrorKind::Unsupported`.
+    ///
+    /// # Safety
+    ///
+    /// Nothing is done with `fd`.
+    pub unsafe fn from_activation_fd(_: RawFd) -> Result<Self, io::Error> {
+        Err(unsupported())
+    }
+
+    /// Drops `fd` and returns an error.
+    pub fn adopt_fd<F: Into<UdpSocket>>(fd: F,  _: bool) -> Result<Self, io::Error> {
+        drop(fd.into());
+        Err(unsupported())
+    }
+
+    pub fn domain(&self) -> Result<AddressFamily, io::Error> {
+        match self.never {}
+    }
+
+    pub fn set_send_checksum_coverage(&self,  _: Option<u16>) -> Result<(), io::Error> {
+        match self.never {}
+    }
+
+    pub fn send_checksum_coverage(&self) -> Result<Option<u16>, io::Error> {
+        match self.never {}
+    }
+
+    pub fn swap_send_checksum_coverage(&self,  _: Option<u16>) -> Result<Option<u16>, io::Error> {
+        match self.never {}
+    }
+
+    pub fn with_send_coverage(&self,  _: Option<u16>) -> Result<CoverageGuard<'_>, io::Error> {
+        match self.never {}
+    }
+
+    pub fn set_recv_checksum_coverage_filter(&self,  _: Option<u16>) -> Result<(), io::Error> {
+        match self.never {}
+    }
+
+    pub fn accept_any_coverage(&self) -> Result<(), io::Error> {
+        match self.never {}
+    }
+
+    pub fn require_full_coverage(&self) -> Result<(), io::Error> {
+        match self.never {}
+    }
+
+    pub fn recv_checksum_coverage_filter(&self) -> Result<Option<u16>, io::Error> {
+        match self.never {}
+    }
+
+    pub fn set_checksum_coverage(&self,  _: Option<u16>,  _: Option<u16>)
+    -> Result<(), io::Error> {
+        match self.never {}
+    }
+
+    pub fn checksum_coverage(&self) -> Result<(Option<u16>, Option<u16>), io::Error> {
+        match self.never {}
+    }
+
+    pub fn filter_sanity_check(&self) -> Result<Option<FilterWarning>, io::Error> {
+        match self.never {}
+    }
+
+    pub fn self_test_partial_coverage(&self) -> Result<bool, io::Error> {
+        match self.never {}
+    }
+
+    pub fn is_bound(&self) -> bool {
+        match self.never {}
+    }
+
+    pub fn is_connected(&self) -> bool {
+        match self.never {}
+    }
+
+    pub fn debug_verbose(&self) -> impl Debug + '_ {
+        match self.never {}
+    }
+
+    pub fn disconnect(&self) -> Result<(), io::Error> {
+        match self.never {}
+    }
+
+    pub fn protocol(&self) -> Result<c_int, io::Error> {
+        match self.never {}
+    }
+
+    pub fn socket_type(&self) -> Result<c_int, io::Error> {
+        match self.never {}
+    }
+
+    pub fn get_sockopt_raw(&self,  _: c_int,  _: c_int,  _: &mut[u8])
+    -> Result<usize, io::Error> {
+        match self.never {}
+    }
+
+    pub fn set_sockopt_raw(&self,  _: c_int,  _: c_int,  _: &[u8]) -> Result<(), io::Error> {
+        match self.never {}
+    }
+
+    pub fn connect_with_coverage<A: ToSocketAddrs>(&self,  _: A,  _: Option<u16>)
+    -> Result<(), io::Error> {
+        match self.never {}
+    }
+
+    pub fn connect_symmetric_coverage<A: ToSocketAddrs>(&self,  _: A,  _: u16)
+    -> Result<(), io::Error> {
+        match self.never {}
+    }
+
+    pub fn connect_and_local_addr<A: ToSocketAddrs>(&self,  _: A)
+    -> Result<SocketAddr, io::Error> {
+        match self.never {}
+    }
+
+    pub fn connect_checked<A: ToSocketAddrs>(&self,  _: A,  _: Duration)
+    -> Result<(), io::Error> {
+        match self.never {}
+    }
+
+    pub fn set_cloexec(&self,  _: bool) -> Result<(), io::Error> {
+        match self.never {}
+    }
+
+    pub fn is_cloexec(&self) -> Result<bool, io::Error> {
+        match self.never {}
+    }
+
+    pub fn set_nonblocking(&self,  _: bool) -> Result<(), io::Error> {
+        match self.never {}
+    }
+
+    pub fn nonblocking(&self) -> Result<bool, io::Error> {
+        match self.never {}
+    }
+
+    pub fn set_dont_route(&self,  _: bool) -> Result<(), io::Error> {
+        match self.never {}
+    }
+
+    pub fn dont_route(&self) -> Result<bool, io::Error> {
+        match self.never {}
+    }
+
+    pub fn set_recv_lowat(&self,  _: usize) -> Result<(), io::Error> {
+        match self.never {}
+    }
+
+    pub fn recv_lowat(&self) -> Result<usize, io::Error> {
+        match self.never {}
+    }
+
+    pub fn set_ttl(&self,  _: u32) -> Result<(), io::Error> {
+        match self.never {}
+    }
+
+    pub fn ttl(&self) -> Result<u32, io::Error> {
+        match self.never {}
+    }
+
+    pub fn set_hop_limit(&self,  _: u32) -> Result<(), io::Error> {
+        match self.never {}
+    }
+
+    pub fn hop_limit(&self) -> Result<u32, io::Error> {
+        match self.never {}
+    }
+
+    pub fn join_multicast_by_ifname(&self,  _: IpAddr,  _: &str) -> Result<(), io::Error> {
+        match self.never {}
+    }
+
+    pub fn recv_queue_len(&self) -> Result<usize, io::Error> {
+        match self.never {}
+    }
+
+    pub fn send_queue_len(&self) -> Result<usize, io::Error> {
+        match self.never {}
+    }
+
+    pub fn set_linger(&self,  _: Option<Duration>) -> Result<(), io::Error> {
+        match self.never {}
+    }
+
+    pub fn set_recv_tos(&self,  _: bool) -> Result<(), io::Error> {
+        match self.never {}
+    }
+
+    pub fn recv_tos(&self) -> Result<bool, io::Error> {
+        match self.never {}
+    }
+
+    pub fn set_recv_pktinfo(&self,  _: bool) -> Result<(), io::Error> {
+        match self.never {}
+    }
+
+    pub fn recv_pktinfo(&self) -> Result<bool, io::Error> {
+        match self.never {}
+    }
+
+    pub fn set_recv_orig_dst(&self,  _: bool) -> Result<(), io::Error> {
+        match self.never {}
+    }
+
+    pub fn recv_orig_dst(&self) -> Result<bool, io::Error> {
+        match self.never {}
+    }
+
+    pub fn set_recv_ttl(&self,  _: bool) -> Result<(), io::Error> {
+        match self.never {}
+    }
+
+    pub fn recv_ttl(&self) -> Result<bool, io::Error> {
+        match self.never {}
+    }
+
+    pub fn set_recv_timestamp(&self,  _: bool) -> Result<(), io::Error> {
+        match self.never {}
+    }
+
+    pub fn recv_timestamp(&self) -> Result<bool, io::Error> {
+        match self.never {}
+    }
+
+    pub fn recv_from_with_tos(&self,  _: &mut[u8])
+    -> Result<(usize, SocketAddr, Option<u8>), io::Error> {
+        match self.never {}
+    }
+
+    pub fn recv_from_uninit(&self,  _: &mut[MaybeUninit<u8>])
+    -> Result<(usize, SocketAddr), io::Error> {
+        match self.never {}
+    }
+
+    pub fn recv_from_array<const N: usize>(&self)
+    -> Result<([u8; N], usize, SocketAddr), io::Error> {
+        match self.never {}
+    }
+
+    pub fn recv_vec(&self,  _: usize) -> Result<(Vec<u8>, SocketAddr), io::Error> {
+        match self.never {}
+    }
+
+    pub fn recv_from_timed(&self,  _: &mut[u8])
+    -> Result<(usize, SocketAddr, Instant), io::Error> {
+        match self.never {}
+    }
+
+    pub fn recv_msg(&self,  _: &mut[u8],  _: RecvOptions) -> Result<RecvInfo, io::Error> {
+        match self.never {}
+    }
+
+    pub fn peek_msg(&self,  _: &mut[u8],  _: RecvOptions) -> Result<RecvInfo, io::Error> {
+        match self.never {}
+    }
+
+    pub fn recv_from_timeout(&self,  _: &mut[u8],  _: Duration)
+    -> Result<Option<(usize, SocketAddr)>, io::Error> {
+        match self.never {}
+    }
+
+    pub fn readable_timeout(&self,  _: Option<Duration>) -> Result<bool, io::Error> {
+        match self.never {}
+    }
+
+    pub fn writable_timeout(&self,  _: Option<Duration>) -> Result<bool, io::Error> {
+        match self.never {}
+    }
+
+    pub fn try_recv_from(&self,  _: &mut[u8])
+    -> Result<Option<(usize, SocketAddr)>, io::Error> {
+        match self.never {}
+    }
+
+    pub fn try_recv(&self,  _: &mut[u8]) -> Result<Option<usize>, io::Error> {
+        match self.never {}
+    }
+
+    pub fn recv_with_flags(&self,  _: &mut[u8],  _: c_int)
+    -> Result<(usize, SocketAddr), io::Error> {
+        match self.never {}
+    }
+
+    pub fn send_to<A: ToSocketAddrs>(&self,  _: &[u8],  _: A) -> Result<usize, io::Error> {
+        match self.never {}
+    }
+
+    pub fn send_to_with_flags(&self,  _: &[u8],  _: c_int,  _: SocketAddr)
+    -> Result<usize, io::Error> {
+        match self.never {}
+    }
+
+    pub fn send_to_scoped(&self,  _: &[u8],  _: Ipv6Addr,  _: u16,  _: u32)
+    -> Result<usize, io::Error> {
+        match self.never {}
+    }
+
+    #[cfg(feature="raw_coverage")]
+    pub fn send_raw_with_coverage_field(&self,  _: &[u8],  _: u16,  _: SocketAddr)
+    -> Result<usize, io::Error> {
+        match self.never {}
+    }
+
+    pub fn send_keepalive_to(&self,  _: SocketAddr) -> Result<(), io::Error> {
+        match self.never {}
+    }
+
+    pub fn send_multicast_v6(&self,  _: &[u8],  _: SocketAddrV6,  _: u32)
+    -> Result<usize, io::Error> {
+        match self.never {}
+    }
+
+    pub fn reply_to(&self,  _: &RecvInfo,  _: &[u8]) -> Result<usize, io::Error> {
+        match self.never {}
+    }
+
+    pub fn send_to_dontfrag(&self,  _: &[u8],  _: SocketAddr) -> Result<usize, io::Error> {
+        match self.never {}
+    }
+
+    pub fn recv_many(&self,  _: &mut[&mut[u8]])
+    -> Result<Vec<(usize, SocketAddr)>, io::Error> {
+        match self.never {}
+    }
+
+    pub fn recv_many_at_least(&self,  _: &mut[&mut[u8]],  _: usize,  _: Option<Duration>)
+    -> Result<Vec<(usize, SocketAddr)>, io::Error> {
+        match self.never {}
+    }
+
+    pub fn recv_many_vectored(&self,  _: &mut[&mut[IoSliceMut<'_>]])
+    -> Result<Vec<(usize, SocketAddr)>, io::Error> {
+        match self.never {}
+    }
+
+    pub fn recv_batch<'a>(&self,  _: &'a mut BatchBuffer)
+    -> Result<impl Iterator<Item=(&'a [u8], SocketAddr)> + 'a, io::Error> {
+        // gives the iterator a type
+        fn never<'a>(never: Never) -> Result<std::iter::Empty<(&'a [u8], SocketAddr)>, io::Error> {
+            match never {}
+        }
+        never(self.never)
+    }
+
+    pub fn send_many(&self,  _: &[(SocketAddr, &[u8])]) -> Result<usize, BatchSendError> {
+        match self.never {}
+    }
+
+    pub fn send_to_many(&self,  _: &[u8],  _: &[SocketAddr])
+    -> Result<usize, BatchSendError> {
+        match self.never {}
+    }
+
+    pub fn send_all_to(&self,  _: &[(SocketAddr, &[u8])]) -> Result<(), io::Error> {
+        match self.never {}
+    }
+
+    pub fn incoming(&self) -> Incoming<'_> {
+        match self.never {}
+    }
+}